@@ -0,0 +1,338 @@
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::Config;
+use crate::crypto::EncryptionKey;
+use crate::error::TasksError;
+use crate::event::{
+	get_next_event_id, get_next_event_id_after, hashmap_to_sorted_vec, split_task_events,
+	EventStore, TaskEvent, TaskEventData,
+};
+use crate::field::materialize_fields;
+use crate::fs::read_file_into_buffer;
+use crate::reducer::{apply_event, create_restored_task, materialize, validate_event, Tasks};
+use crate::search::{parse_search_query, SearchIndex};
+use crate::storage::{
+	acquire_lock, process_event_data, rewrite_events, save_events_batch, write_events_to_paths,
+};
+use crate::task::{Task, TaskId};
+use crate::util::get_archive_paths;
+use crate::view::materialize_views;
+
+/// Closed tasks whose last status change is more than `after_days` old,
+/// leaving out any which a task staying active is nested under or depends on.
+pub fn get_archivable_task_ids(
+	tasks: &Tasks,
+	now: DateTime<Utc>,
+	after_days: u32,
+) -> BTreeSet<TaskId> {
+	let cutoff = now - Duration::days(after_days as i64);
+	let mut task_ids: BTreeSet<TaskId> = tasks
+		.values()
+		.filter(|task| task.status.is_closed())
+		.filter(|task| {
+			task.status_history
+				.last()
+				.map_or(false, |change| change.at < cutoff)
+		})
+		.map(|task| task.id)
+		.collect();
+
+	loop {
+		let referenced: Vec<TaskId> = tasks
+			.values()
+			.filter(|task| !task_ids.contains(&task.id))
+			.flat_map(|task| task.parent.iter().chain(&task.depends_on))
+			.filter(|task_id| task_ids.contains(task_id))
+			.copied()
+			.collect();
+		if referenced.is_empty() {
+			return task_ids;
+		}
+		for task_id in referenced {
+			task_ids.remove(&task_id);
+		}
+	}
+}
+
+/// Merges the archive replicas, since the archive is only read on demand.
+/// Like `load_events`, replicas which can't be decrypted are skipped unless none can be.
+pub fn load_archive(
+	config: &Config,
+	encryption_key: &EncryptionKey,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let mut events = HashMap::new();
+	let mut readable = false;
+	let mut first_error = None;
+	for archive_path in get_archive_paths(config) {
+		if let Ok(encrypted_data) = read_file_into_buffer(&archive_path) {
+			match process_event_data(&encrypted_data, encryption_key) {
+				Ok(archive_events) => {
+					readable = true;
+					for event in archive_events {
+						events.insert(event.id, event);
+					}
+				}
+				Err(error) => {
+					first_error.get_or_insert(error);
+				}
+			}
+		}
+	}
+	match first_error {
+		Some(error) if !readable => Err(error),
+		_ => Ok(hashmap_to_sorted_vec(&events)),
+	}
+}
+
+pub fn save_archive(
+	config: &Config,
+	events: Vec<TaskEvent>,
+	encryption_key: &EncryptionKey,
+) -> Result<(), TasksError> {
+	let _lock = acquire_lock(&config.context)?;
+	write_archive(config, events, encryption_key)
+}
+
+/// The caller must hold the lock.
+pub fn write_archive(
+	config: &Config,
+	events: Vec<TaskEvent>,
+	encryption_key: &EncryptionKey,
+) -> Result<(), TasksError> {
+	write_events_to_paths(get_archive_paths(config), events, encryption_key)
+}
+
+/// Copies the archive to any newly enabled locations.
+pub fn replicate_archive(
+	config: &Config,
+	encryption_key: &EncryptionKey,
+) -> Result<(), TasksError> {
+	if get_archive_paths(config).iter().any(|path| path.exists()) {
+		save_archive(
+			config,
+			load_archive(config, encryption_key)?,
+			encryption_key,
+		)?;
+	}
+	Ok(())
+}
+
+pub fn load_archived_tasks(
+	config: &Config,
+	encryption_key: &EncryptionKey,
+) -> Result<Tasks, TasksError> {
+	Ok(materialize(&load_archive(config, encryption_key)?))
+}
+
+pub fn search_archived_tasks(
+	query: &str,
+	config: &Config,
+	encryption_key: &EncryptionKey,
+) -> Result<Vec<Task>, TasksError> {
+	let archived = load_archived_tasks(config, encryption_key)?;
+	Ok(SearchIndex::build(&archived)
+		.search(&parse_search_query(query))
+		.into_iter()
+		.filter_map(|task_id| archived.get(&task_id).cloned())
+		.collect())
+}
+
+/// Moves the events of old closed tasks into the archive, leaving an `ArchiveTask` event for each.
+/// Everything happens under one lock on the merged replicas. The archive is written first,
+/// so a failure can leave events in both but never in neither.
+pub fn archive_tasks(
+	config: &Config,
+	now: DateTime<Utc>,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let mut archive_events = Vec::new();
+	rewrite_events(config, encryption_key, event_store, |events| {
+		let mut tasks = materialize(&events);
+		let task_ids = get_archivable_task_ids(&tasks, now, config.archive_after_days);
+		if task_ids.is_empty() {
+			return Ok(events);
+		}
+
+		let fields = materialize_fields(&events);
+		let views = materialize_views(&events);
+		let first_id = get_next_event_id_after(&events);
+		for (i, task_id) in task_ids.iter().enumerate() {
+			let event = TaskEvent {
+				id: first_id + i as u64,
+				data: TaskEventData::ArchiveTask(*task_id),
+			};
+			validate_event(&tasks, &event, &config.workflow, &fields, &views)?;
+			apply_event(&mut tasks, &event);
+			archive_events.push(event);
+		}
+
+		let (mut active_events, archived_events) = split_task_events(events, &task_ids);
+		let mut archive = load_archive(config, encryption_key)?;
+		archive.extend(archived_events);
+		archive.sort();
+		write_archive(config, archive, encryption_key)?;
+
+		active_events.extend(archive_events.iter().cloned());
+		Ok(active_events)
+	})?;
+	Ok(archive_events)
+}
+
+/// Recreates the task with a new `CreateTask` event, which is returned, then drops it from the archive.
+pub fn restore_task(
+	config: &Config,
+	task_id: TaskId,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<TaskEvent, TasksError> {
+	let archive = load_archive(config, encryption_key)?;
+	let archived = materialize(&archive).remove(&task_id).ok_or_else(|| {
+		TasksError::InvalidEventError(format!("Task {} is not archived", task_id))
+	})?;
+
+	let event = TaskEvent {
+		id: get_next_event_id(&event_store.events.lock().unwrap()),
+		data: TaskEventData::CreateTask(create_restored_task(&event_store.tasks(), &archived)),
+	};
+	save_events_batch(config, vec![event.clone()], encryption_key, event_store)?;
+
+	let task_ids = BTreeSet::from([task_id]);
+	let (remaining, _) = split_task_events(archive, &task_ids);
+	save_archive(config, remaining, encryption_key)?;
+	Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::context::AppContext;
+	use crate::event::EventId;
+	use crate::fs::write_buffer_to_file;
+	use crate::storage::save_events;
+	use crate::workflow::{Status, StatusChange};
+	use tempfile::tempdir;
+
+	fn create_task(id: TaskId, status: Status, closed_at: &str) -> Task {
+		Task {
+			id,
+			status,
+			status_history: vec![StatusChange {
+				status,
+				at: closed_at.parse().unwrap(),
+			}],
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_get_archivable_task_ids() {
+		let now: DateTime<Utc> = "2026-10-19T12:00:00Z".parse().unwrap();
+		let tasks = Tasks::from([
+			(1, create_task(1, Status::Done, "2026-08-01T00:00:00Z")),
+			(2, create_task(2, Status::Cancelled, "2026-08-01T00:00:00Z")),
+			(3, create_task(3, Status::Done, "2026-10-18T00:00:00Z")),
+			(4, create_task(4, Status::Todo, "2026-08-01T00:00:00Z")),
+			// Still the parent of an active task, which keeps its own parent active too
+			(5, create_task(5, Status::Done, "2026-08-01T00:00:00Z")),
+			(
+				6,
+				Task {
+					parent: Some(5),
+					..create_task(6, Status::Done, "2026-08-01T00:00:00Z")
+				},
+			),
+			(
+				7,
+				Task {
+					parent: Some(6),
+					..create_task(7, Status::Done, "2026-10-18T00:00:00Z")
+				},
+			),
+			(
+				8,
+				Task {
+					depends_on: vec![2],
+					..create_task(8, Status::Done, "2026-08-01T00:00:00Z")
+				},
+			),
+		]);
+
+		let task_ids = get_archivable_task_ids(&tasks, now, 30);
+
+		assert_eq!(task_ids, BTreeSet::from([1, 2, 8]));
+	}
+
+	#[test]
+	fn test_archive_tasks_merges_replicas_first() {
+		let dir = tempdir().unwrap();
+		let config = Config {
+			dropbox_enabled: true,
+			..Config::new(&AppContext::new(dir.path()))
+		};
+		let encryption_key = EncryptionKey::default();
+		let create = |id: EventId, task: Task| TaskEvent {
+			id,
+			data: TaskEventData::CreateTask(task),
+		};
+		let event_store = EventStore::new();
+		save_events_batch(
+			&config,
+			vec![create(
+				1,
+				create_task(1, Status::Done, "2026-08-01T00:00:00Z"),
+			)],
+			&encryption_key,
+			&event_store,
+		)
+		.unwrap();
+		// Written by another device since this one loaded
+		save_events(
+			&config,
+			vec![
+				create(1, create_task(1, Status::Done, "2026-08-01T00:00:00Z")),
+				create(2, create_task(2, Status::Done, "2026-08-02T00:00:00Z")),
+			],
+			&encryption_key,
+		)
+		.unwrap();
+		let archived_earlier = create(0, create_task(3, Status::Done, "2026-01-01T00:00:00Z"));
+		save_archive(&config, vec![archived_earlier], &encryption_key).unwrap();
+		write_buffer_to_file(&get_archive_paths(&config)[1], b"half synced").unwrap();
+
+		let archive_events = archive_tasks(
+			&config,
+			"2026-10-19T12:00:00Z".parse().unwrap(),
+			&encryption_key,
+			&event_store,
+		)
+		.unwrap();
+
+		assert_eq!(
+			archive_events
+				.iter()
+				.map(|event| event.data.clone())
+				.collect::<Vec<_>>(),
+			vec![TaskEventData::ArchiveTask(1), TaskEventData::ArchiveTask(2)]
+		);
+		assert_eq!(
+			load_archive(&config, &encryption_key)
+				.unwrap()
+				.iter()
+				.map(|event| event.id)
+				.collect::<Vec<_>>(),
+			vec![0, 1, 2]
+		);
+		assert_eq!(
+			hashmap_to_sorted_vec(&event_store.events.lock().unwrap()),
+			archive_events
+		);
+
+		for path in get_archive_paths(&config) {
+			write_buffer_to_file(&path, b"truncated").unwrap();
+		}
+		assert!(load_archive(&config, &encryption_key).is_err());
+	}
+}
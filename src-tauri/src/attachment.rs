@@ -0,0 +1,374 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{read_dir, remove_file};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use filetime::{set_file_mtime, FileTime};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{decrypt_bytes, encrypt, keyed_hash, EncryptionKey, ENCRYPTION_KEY_SIZE};
+use crate::error::TasksError;
+use crate::event::{TaskEvent, TaskEventData};
+use crate::fs::{read_file_into_buffer, write_buffer_to_file};
+use crate::lockfile::VaultLock;
+use crate::util::to_hex_string;
+
+/// Unreferenced attachments younger than this are kept, since the events referencing them may still be syncing.
+pub const ATTACHMENT_GC_GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+	/// SHA-256 of the unencrypted content. The file name is derived from it with the key, so that
+	/// the content can't be confirmed from the file names alone.
+	pub hash: String,
+	pub name: String,
+	pub size: u64,
+}
+
+pub fn hash_content(data: &[u8]) -> String {
+	to_hex_string(&Sha256::digest(data))
+}
+
+pub fn get_file_name(hash: &str, key: &[u8; ENCRYPTION_KEY_SIZE]) -> String {
+	to_hex_string(&keyed_hash(key, hash.as_bytes()))
+}
+
+/// File names have the same form as hashes.
+pub fn is_attachment_hash(name: &str) -> bool {
+	name.len() == 64
+		&& name
+			.chars()
+			.all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+/// Validates the hash first, since it may come from the frontend and is used as a file name.
+fn get_attachment_paths(
+	dirs: &[PathBuf],
+	hash: &str,
+	key: &[u8; ENCRYPTION_KEY_SIZE],
+) -> Result<Vec<PathBuf>, TasksError> {
+	if !is_attachment_hash(hash) {
+		return Err(TasksError::UnknownError(format!(
+			"Invalid attachment hash: {}",
+			hash
+		)));
+	}
+	let file_name = get_file_name(hash, key);
+	Ok(dirs.iter().map(|dir| dir.join(&file_name)).collect())
+}
+
+fn list_attachment_file_names(dir: &PathBuf) -> BTreeSet<String> {
+	read_dir(dir)
+		.map(|entries| {
+			entries
+				.filter_map(|entry| entry.ok())
+				.filter_map(|entry| entry.file_name().into_string().ok())
+				.filter(|file_name| is_attachment_hash(file_name))
+				.collect()
+		})
+		.unwrap_or_default()
+}
+
+/// Encrypts the content into each directory, skipping those which already contain it. Existing copies
+/// are touched instead, so that garbage collection counts them as new again.
+pub fn save_attachment(
+	dirs: &[PathBuf],
+	name: &str,
+	data: &[u8],
+	encryption_key: &EncryptionKey,
+) -> Result<Attachment, TasksError> {
+	let key = encryption_key.0.lock().unwrap();
+	let hash = hash_content(data);
+	let (existing_paths, missing_paths): (Vec<PathBuf>, Vec<PathBuf>) =
+		get_attachment_paths(dirs, &hash, &key)?
+			.into_iter()
+			.partition(|path| path.exists());
+	for path in existing_paths {
+		set_file_mtime(&path, FileTime::now())?;
+	}
+	if !missing_paths.is_empty() {
+		let encrypted = encrypt(data, &key)?;
+		for path in missing_paths {
+			write_buffer_to_file(&path, &encrypted)?;
+		}
+	}
+
+	Ok(Attachment {
+		hash,
+		name: name.to_string(),
+		size: data.len() as u64,
+	})
+}
+
+/// Reads from the first directory with an intact copy.
+pub fn load_attachment(
+	dirs: &[PathBuf],
+	hash: &str,
+	encryption_key: &EncryptionKey,
+) -> Result<Vec<u8>, TasksError> {
+	let key = encryption_key.0.lock().unwrap();
+	get_attachment_paths(dirs, hash, &key)?
+		.iter()
+		.filter_map(|path| read_file_into_buffer(path).ok())
+		.filter_map(|encrypted| decrypt_bytes(&encrypted, &key).ok())
+		.find(|data| hash_content(data) == hash)
+		.ok_or_else(|| TasksError::UnknownError(format!("Attachment not found: {}", hash)))
+}
+
+/// Includes attachments of deleted tasks and earlier versions, as long as their events remain.
+pub fn get_referenced_hashes(events: &[TaskEvent]) -> HashSet<String> {
+	events
+		.iter()
+		.filter_map(|event| match &event.data {
+			TaskEventData::CreateTask(task) | TaskEventData::UpdateTask(task) => Some(task),
+			_ => None,
+		})
+		.flat_map(|task| {
+			task.attachments
+				.iter()
+				.map(|attachment| attachment.hash.clone())
+		})
+		.collect()
+}
+
+/// Copies the encrypted files as they are to the directories which lack them.
+pub fn replicate_attachments(dirs: &[PathBuf]) -> Result<(), TasksError> {
+	let file_names: BTreeSet<String> = dirs.iter().flat_map(list_attachment_file_names).collect();
+	for file_name in file_names {
+		let paths: Vec<PathBuf> = dirs.iter().map(|dir| dir.join(&file_name)).collect();
+		let (existing, missing): (Vec<&PathBuf>, Vec<&PathBuf>) =
+			paths.iter().partition(|path| path.exists());
+		if missing.is_empty() {
+			continue;
+		}
+		if let Some(encrypted) = existing
+			.iter()
+			.find_map(|path| read_file_into_buffer(path).ok())
+		{
+			for path in missing {
+				write_buffer_to_file(path, &encrypted)?;
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Removes attachments which no event references, returning their file names.
+/// The caller must hold the lock, so that no attachment is saved in between.
+pub fn collect_garbage(
+	dirs: &[PathBuf],
+	referenced: &HashSet<String>,
+	encryption_key: &EncryptionKey,
+	now: SystemTime,
+) -> Result<Vec<String>, TasksError> {
+	let key = encryption_key.0.lock().unwrap();
+	let referenced_file_names: HashSet<String> = referenced
+		.iter()
+		.map(|hash| get_file_name(hash, &key))
+		.collect();
+	let mut removed = BTreeSet::new();
+	for dir in dirs {
+		for file_name in list_attachment_file_names(dir) {
+			if referenced_file_names.contains(&file_name) {
+				continue;
+			}
+			let path = dir.join(&file_name);
+			let modified = path.metadata().and_then(|metadata| metadata.modified())?;
+			if modified + ATTACHMENT_GC_GRACE_PERIOD < now {
+				remove_file(&path)?;
+				removed.insert(file_name);
+			}
+		}
+	}
+	Ok(removed.into_iter().collect())
+}
+
+/// Used when the password changes, moving each file to its name under the new key.
+/// Files which do not decrypt with the old key are left alone. There may be many,
+/// so the lock is refreshed before each.
+pub fn reencrypt_attachments(
+	dirs: &[PathBuf],
+	old_key: &[u8; ENCRYPTION_KEY_SIZE],
+	new_key: &[u8; ENCRYPTION_KEY_SIZE],
+	lock: &VaultLock,
+) -> Result<(), TasksError> {
+	for dir in dirs {
+		for file_name in list_attachment_file_names(dir) {
+			lock.refresh()?;
+			let path = dir.join(&file_name);
+			if let Ok(data) = decrypt_bytes(&read_file_into_buffer(&path)?, old_key) {
+				let new_path = dir.join(get_file_name(&hash_content(&data), new_key));
+				write_buffer_to_file(&new_path, &encrypt(&data, new_key)?)?;
+				if new_path != path {
+					remove_file(&path)?;
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::task::Task;
+	use std::fs::File;
+	use std::sync::Mutex;
+	use tempfile::tempdir;
+
+	fn create_encryption_key(byte: u8) -> EncryptionKey {
+		EncryptionKey(Mutex::new([byte; ENCRYPTION_KEY_SIZE]))
+	}
+
+	#[test]
+	fn test_hash_content() {
+		let hash = hash_content(b"hello");
+		assert_eq!(
+			hash,
+			"2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+		);
+		assert!(is_attachment_hash(&hash));
+		assert!(!is_attachment_hash("../tasks"));
+	}
+
+	#[test]
+	fn test_save_and_load_attachment() {
+		let dir = tempdir().unwrap();
+		let dirs = vec![dir.path().join("local"), dir.path().join("dropbox")];
+		let encryption_key = create_encryption_key(1);
+
+		let attachment = save_attachment(&dirs, "receipt.pdf", b"%PDF", &encryption_key).unwrap();
+
+		assert_eq!(attachment.name, "receipt.pdf");
+		assert_eq!(attachment.size, 4);
+		let file_name = get_file_name(&attachment.hash, &encryption_key.0.lock().unwrap());
+		assert_ne!(file_name, attachment.hash);
+		assert!(dirs.iter().all(|dir| dir.join(&file_name).exists()));
+		assert_eq!(
+			load_attachment(&dirs, &attachment.hash, &encryption_key).unwrap(),
+			b"%PDF"
+		);
+		assert!(load_attachment(&dirs, &attachment.hash, &create_encryption_key(2)).is_err());
+		assert!(load_attachment(&dirs, "../../tasks", &encryption_key).is_err());
+	}
+
+	#[test]
+	fn test_load_attachment_falls_back_to_intact_copy() {
+		let dir = tempdir().unwrap();
+		let dirs = vec![dir.path().join("local"), dir.path().join("dropbox")];
+		let encryption_key = create_encryption_key(1);
+		let attachment = save_attachment(&dirs, "notes.txt", b"notes", &encryption_key).unwrap();
+		let file_name = get_file_name(&attachment.hash, &encryption_key.0.lock().unwrap());
+		write_buffer_to_file(&dirs[0].join(file_name), b"truncated").unwrap();
+
+		assert_eq!(
+			load_attachment(&dirs, &attachment.hash, &encryption_key).unwrap(),
+			b"notes"
+		);
+	}
+
+	#[test]
+	fn test_replicate_attachments() {
+		let dir = tempdir().unwrap();
+		let dirs = vec![dir.path().join("local"), dir.path().join("dropbox")];
+		let encryption_key = create_encryption_key(1);
+		let attachment =
+			save_attachment(&dirs[..1], "notes.txt", b"notes", &encryption_key).unwrap();
+
+		replicate_attachments(&dirs).unwrap();
+
+		assert_eq!(
+			load_attachment(&dirs[1..], &attachment.hash, &encryption_key).unwrap(),
+			b"notes"
+		);
+	}
+
+	#[test]
+	fn test_collect_garbage() {
+		let dir = tempdir().unwrap();
+		let dirs = vec![dir.path().to_path_buf()];
+		let encryption_key = create_encryption_key(1);
+		let kept = save_attachment(&dirs, "kept.txt", b"kept", &encryption_key).unwrap();
+		let unreferenced = save_attachment(&dirs, "old.txt", b"old", &encryption_key).unwrap();
+		File::create(dir.path().join("tasks")).unwrap();
+
+		let events = vec![TaskEvent {
+			id: 1,
+			data: TaskEventData::CreateTask(Task {
+				id: 1,
+				attachments: vec![kept.clone()],
+				..Default::default()
+			}),
+		}];
+		let referenced = get_referenced_hashes(&events);
+
+		assert!(
+			collect_garbage(&dirs, &referenced, &encryption_key, SystemTime::now())
+				.unwrap()
+				.is_empty()
+		);
+
+		let key = *encryption_key.0.lock().unwrap();
+		let later = SystemTime::now() + ATTACHMENT_GC_GRACE_PERIOD * 2;
+		assert_eq!(
+			collect_garbage(&dirs, &referenced, &encryption_key, later).unwrap(),
+			vec![get_file_name(&unreferenced.hash, &key)]
+		);
+		assert!(dir.path().join(get_file_name(&kept.hash, &key)).exists());
+		assert!(!dir
+			.path()
+			.join(get_file_name(&unreferenced.hash, &key))
+			.exists());
+		assert!(dir.path().join("tasks").exists());
+	}
+
+	#[test]
+	fn test_save_attachment_touches_existing_copies() {
+		let dir = tempdir().unwrap();
+		let dirs = vec![dir.path().to_path_buf()];
+		let encryption_key = create_encryption_key(1);
+		let attachment = save_attachment(&dirs, "old.txt", b"old", &encryption_key).unwrap();
+		let path = dir.path().join(get_file_name(
+			&attachment.hash,
+			&encryption_key.0.lock().unwrap(),
+		));
+		let long_ago = SystemTime::now() - ATTACHMENT_GC_GRACE_PERIOD * 2;
+		set_file_mtime(&path, FileTime::from_system_time(long_ago)).unwrap();
+
+		save_attachment(&dirs, "old.txt", b"old", &encryption_key).unwrap();
+
+		assert!(
+			collect_garbage(&dirs, &HashSet::new(), &encryption_key, SystemTime::now())
+				.unwrap()
+				.is_empty()
+		);
+		assert!(path.exists());
+	}
+
+	#[test]
+	fn test_reencrypt_attachments() {
+		let dir = tempdir().unwrap();
+		let dirs = vec![dir.path().to_path_buf()];
+		let old_key = create_encryption_key(1);
+		let new_key = create_encryption_key(2);
+		let attachment = save_attachment(&dirs, "notes.txt", b"notes", &old_key).unwrap();
+		let lock = VaultLock::acquire(&dir.path().join("lock")).unwrap();
+
+		reencrypt_attachments(
+			&dirs,
+			&old_key.0.lock().unwrap(),
+			&new_key.0.lock().unwrap(),
+			&lock,
+		)
+		.unwrap();
+
+		assert!(load_attachment(&dirs, &attachment.hash, &old_key).is_err());
+		assert_eq!(list_attachment_file_names(&dirs[0]).len(), 1);
+		assert_eq!(
+			load_attachment(&dirs, &attachment.hash, &new_key).unwrap(),
+			b"notes"
+		);
+	}
+}
@@ -0,0 +1,169 @@
+use chrono::{DateTime, Duration, TimeZone};
+
+use crate::due::is_overdue;
+use crate::event::{EventId, TaskEvent, TaskEventData};
+use crate::reducer::Tasks;
+use crate::task::{Deadline, Task, TaskId, ZonedDateTime};
+use crate::workflow::Status;
+
+fn create_update_events(updated: Vec<Task>, first_id: EventId) -> Vec<TaskEvent> {
+	updated
+		.into_iter()
+		.enumerate()
+		.map(|(i, task)| TaskEvent {
+			id: first_id + i as EventId,
+			data: TaskEventData::UpdateTask(task),
+		})
+		.collect()
+}
+
+/// Moves overdue tasks to tomorrow, keeping the time of day of zoned deadlines.
+/// Zoned deadlines move to tomorrow in their own time zone, which can differ from `now`'s date.
+pub fn create_postpone_overdue_events<Z: TimeZone>(
+	tasks: &Tasks,
+	now: &DateTime<Z>,
+	first_id: EventId,
+) -> Vec<TaskEvent> {
+	let tomorrow = now.date_naive() + Duration::days(1);
+	let postponed = tasks
+		.values()
+		.filter(|task| is_overdue(task, now))
+		.map(|task| {
+			let deadline = match task.deadline {
+				Some(Deadline::Zoned(zoned)) => Deadline::Zoned(ZonedDateTime {
+					local: (now.with_timezone(&zoned.time_zone).date_naive() + Duration::days(1))
+						.and_time(zoned.local.time()),
+					..zoned
+				}),
+				_ => Deadline::Date(tomorrow),
+			};
+			Task {
+				deadline: Some(deadline),
+				..task.clone()
+			}
+		})
+		.collect();
+	create_update_events(postponed, first_id)
+}
+
+/// Skips tasks which do not exist or already have the status.
+pub fn create_set_status_events(
+	tasks: &Tasks,
+	task_ids: &[TaskId],
+	status: Status,
+	first_id: EventId,
+) -> Vec<TaskEvent> {
+	let updated = task_ids
+		.iter()
+		.filter_map(|task_id| tasks.get(task_id))
+		.filter(|task| task.status != status)
+		.map(|task| Task {
+			status,
+			..task.clone()
+		})
+		.collect();
+	create_update_events(updated, first_id)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::Utc;
+
+	fn create_tasks() -> Tasks {
+		let create_task = |id: TaskId, deadline: Option<Deadline>, status: Status| Task {
+			id,
+			deadline,
+			status,
+			..Default::default()
+		};
+		let date = |date: &str| Some(Deadline::Date(date.parse().unwrap()));
+		Tasks::from([
+			(1, create_task(1, date("2026-10-19"), Status::Todo)),
+			(2, create_task(2, date("2026-10-19"), Status::Done)),
+			(3, create_task(3, date("2026-10-25"), Status::Todo)),
+			(
+				4,
+				create_task(
+					4,
+					Some(Deadline::Zoned(ZonedDateTime {
+						local: "2026-10-20T09:30:00".parse().unwrap(),
+						time_zone: chrono_tz::Europe::London,
+					})),
+					Status::InProgress,
+				),
+			),
+			(
+				5,
+				create_task(
+					5,
+					Some(Deadline::Zoned(ZonedDateTime {
+						local: "2026-10-21T09:00:00".parse().unwrap(),
+						time_zone: chrono_tz::Pacific::Auckland,
+					})),
+					Status::Todo,
+				),
+			),
+		])
+	}
+
+	#[test]
+	fn test_create_postpone_overdue_events() {
+		let tasks = create_tasks();
+		let now: DateTime<Utc> = "2026-10-21T12:00:00Z".parse().unwrap();
+
+		let events = create_postpone_overdue_events(&tasks, &now, 100);
+
+		assert_eq!(
+			events,
+			vec![
+				TaskEvent {
+					id: 100,
+					data: TaskEventData::UpdateTask(Task {
+						deadline: Some(Deadline::Date("2026-10-22".parse().unwrap())),
+						..tasks[&1].clone()
+					}),
+				},
+				TaskEvent {
+					id: 101,
+					data: TaskEventData::UpdateTask(Task {
+						deadline: Some(Deadline::Zoned(ZonedDateTime {
+							local: "2026-10-22T09:30:00".parse().unwrap(),
+							time_zone: chrono_tz::Europe::London,
+						})),
+						..tasks[&4].clone()
+					}),
+				},
+				// Already the 22nd in Auckland
+				TaskEvent {
+					id: 102,
+					data: TaskEventData::UpdateTask(Task {
+						deadline: Some(Deadline::Zoned(ZonedDateTime {
+							local: "2026-10-23T09:00:00".parse().unwrap(),
+							time_zone: chrono_tz::Pacific::Auckland,
+						})),
+						..tasks[&5].clone()
+					}),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_create_set_status_events() {
+		let tasks = create_tasks();
+
+		let events = create_set_status_events(&tasks, &[1, 2, 9], Status::Done, 100);
+
+		assert_eq!(
+			events,
+			vec![TaskEvent {
+				id: 100,
+				data: TaskEventData::UpdateTask(Task {
+					status: Status::Done,
+					..tasks[&1].clone()
+				}),
+			}]
+		);
+	}
+}
@@ -1,14 +1,48 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::slice;
+use std::time::SystemTime;
+
+use chrono::{Local, NaiveDate, Utc};
 use tauri::State;
 
-use crate::config::{AppConfig, Config};
-use crate::crypto::{derive_key, EncryptionKey};
+use crate::archive;
+use crate::attachment::{self, Attachment};
+use crate::bulk::{create_postpone_overdue_events, create_set_status_events};
+use crate::config::{AppConfig, Config, ConfigData};
+use crate::context::AppContext;
+use crate::crypto::EncryptionKey;
+use crate::dependency::{get_blocked_task_ids, get_next_actionable_tasks};
+use crate::due::{group_tasks_by_due, DueGroup};
 use crate::error::TasksError;
-use crate::event::{hashmap_to_sorted_vec, EventStore, TaskEvent};
-use crate::storage::{self, load_salt};
+use crate::event::{
+	get_next_event_id, hashmap_to_sorted_vec, EventStore, TaskEvent, TaskEventData,
+};
+use crate::field::{materialize_fields, FieldDefinition, FieldId};
+use crate::filter::{filter_tasks, parse_filter};
+use crate::markdown::{self, get_all_links, RenderedDetails, TaskLink};
+use crate::organize::{
+	create_rename_tag_events, get_all_tags, get_tasks_in_project, get_tasks_with_priority,
+	get_tasks_with_tag,
+};
+use crate::reducer::materialize;
+use crate::replica::{self, ReplicaStatus};
+use crate::search::{parse_search_query, SearchIndex, SearchStore};
+use crate::storage;
+use crate::subtask::{get_progress, get_subtasks, Progress};
+use crate::task::{Priority, Task, TaskId};
+use crate::timetracking::{
+	get_local_date_range, get_running_timer, get_time_report, TimeEntry, TimeReport, TimerEvent,
+};
+use crate::trash::{self, TrashedTask};
+use crate::util::get_attachments_dirs;
+use crate::vault::{self, Vault, VaultId, VaultRegistry};
+use crate::view::{evaluate_view, materialize_views, View, ViewId};
+use crate::workflow::{get_cycle_time, Status};
 
 #[tauri::command]
-pub fn check_exists(app_config: State<AppConfig>) -> Result<bool, TasksError> {
-	let config = app_config.config.lock().unwrap();
+pub fn check_exists(app_context: State<AppContext>) -> Result<bool, TasksError> {
+	let config = storage::load_config(&app_context, vault::load_registry(&app_context).active);
 	storage::check_exists(&config)
 }
 
@@ -16,17 +50,22 @@ pub fn check_exists(app_config: State<AppConfig>) -> Result<bool, TasksError> {
 pub fn unlock(
 	password: &str,
 	encryption_key: State<EncryptionKey>,
+	app_context: State<AppContext>,
 	app_config: State<AppConfig>,
+	event_store: State<EventStore>,
 ) -> Result<Config, TasksError> {
 	let mut config = app_config.config.lock().unwrap();
-	*config = storage::load_config();
+	*config = storage::load_config(&app_context, vault::load_registry(&app_context).active);
 
-	let salt = load_salt(&config)?;
-	derive_key(password, &salt, &mut encryption_key.0.lock().unwrap())?;
+	storage::unlock(&config, password, &encryption_key, &event_store)?;
 
 	if !storage::check_exists(&config)? {
 		storage::save_events(&config, Vec::new(), &encryption_key)?;
 	}
+	attachment::replicate_attachments(&get_attachments_dirs(&config))?;
+	archive::replicate_archive(&config, &encryption_key)?;
+	// Applied on every unlock, so the retention period holds without the user doing anything
+	trash::purge_trash(&config, Utc::now(), &encryption_key, &event_store)?;
 	Ok(config.clone())
 }
 
@@ -34,13 +73,24 @@ pub fn unlock(
 pub fn lock(
 	encryption_key: State<EncryptionKey>,
 	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
 ) -> Result<(), TasksError> {
+	lock_vault(&encryption_key, &event_store, &search_store);
+	Ok(())
+}
+
+fn lock_vault(
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+	search_store: &SearchStore,
+) {
 	for byte in encryption_key.0.lock().unwrap().iter_mut() {
 		*byte = 0;
 	}
 	let mut events = event_store.events.lock().unwrap();
 	events.clear();
-	Ok(())
+	event_store.unannounced.lock().unwrap().clear();
+	search_store.index.lock().unwrap().clear();
 }
 
 #[tauri::command]
@@ -55,21 +105,29 @@ pub fn change_password(
 	storage::change_password(&config, current, new, &encryption_key, &event_store)
 }
 
+/// The password is only needed when a newly enabled location was encrypted under a different salt.
 #[tauri::command]
 pub fn update_config(
-	new_config: Config,
+	new_config: ConfigData,
+	password: Option<String>,
 	app_config: State<AppConfig>,
 	encryption_key: State<EncryptionKey>,
 	event_store: State<EventStore>,
 ) -> Result<(), TasksError> {
 	let mut config = app_config.config.lock().unwrap();
-	*config = new_config;
-	storage::save_config(&config)?;
+	let updated_config = new_config.into_config(&config.context, config.vault_id);
+	// Synced first, so the new locations are never written to before their contents are merged
+	storage::sync_replicas(
+		&updated_config,
+		password.as_deref(),
+		&encryption_key,
+		&event_store,
+	)?;
+	storage::save_config(&updated_config)?;
+	*config = updated_config;
 
-	let salt = storage::load_salt(&config)?;
-	storage::save_salt(&config, &salt)?;
-	let events = event_store.events.lock().unwrap();
-	storage::save_events(&config, hashmap_to_sorted_vec(&events), &encryption_key)
+	archive::replicate_archive(&config, &encryption_key)?;
+	attachment::replicate_attachments(&get_attachments_dirs(&config))
 }
 
 #[tauri::command]
@@ -78,9 +136,100 @@ pub fn save_event(
 	app_config: State<AppConfig>,
 	encryption_key: State<EncryptionKey>,
 	event_store: State<EventStore>,
-) -> Result<(), TasksError> {
+	search_store: State<SearchStore>,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let follow_up_events =
+		storage::save_event(&config, event.clone(), &encryption_key, &event_store)?;
+
+	let mut saved_events = vec![event];
+	saved_events.extend(follow_up_events.iter().cloned());
+	index_saved_events(&saved_events, &event_store, &search_store);
+	Ok(follow_up_events)
+}
+
+fn index_saved_events(events: &[TaskEvent], event_store: &EventStore, search_store: &SearchStore) {
+	search_store
+		.index
+		.lock()
+		.unwrap()
+		.apply_events(&event_store.tasks(), events);
+}
+
+/// Saves all of the events with a single write, or none of them. Returns any follow-up events.
+#[tauri::command]
+pub fn save_events_batch(
+	events: Vec<TaskEvent>,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let follow_up_events =
+		storage::save_events_batch(&config, events.clone(), &encryption_key, &event_store)?;
+
+	let mut saved_events = events;
+	saved_events.extend(follow_up_events.iter().cloned());
+	index_saved_events(&saved_events, &event_store, &search_store);
+	Ok(follow_up_events)
+}
+
+/// Returns every saved event, including follow-ups.
+fn save_generated_events(
+	events: Vec<TaskEvent>,
+	config: &Config,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+	search_store: &SearchStore,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let follow_up_events =
+		storage::save_events_batch(config, events.clone(), encryption_key, event_store)?;
+
+	let mut saved_events = events;
+	saved_events.extend(follow_up_events);
+	index_saved_events(&saved_events, event_store, search_store);
+	Ok(saved_events)
+}
+
+#[tauri::command]
+pub fn postpone_overdue(
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
+) -> Result<Vec<TaskEvent>, TasksError> {
 	let config = app_config.config.lock().unwrap();
-	storage::save_event(&config, event, &encryption_key, &event_store)
+	let first_id = get_next_event_id(&event_store.events.lock().unwrap());
+	let events = create_postpone_overdue_events(&event_store.tasks(), &Local::now(), first_id);
+	save_generated_events(
+		events,
+		&config,
+		&encryption_key,
+		&event_store,
+		&search_store,
+	)
+}
+
+#[tauri::command]
+pub fn set_status_bulk(
+	task_ids: Vec<TaskId>,
+	status: Status,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let first_id = get_next_event_id(&event_store.events.lock().unwrap());
+	let events = create_set_status_events(&event_store.tasks(), &task_ids, status, first_id);
+	save_generated_events(
+		events,
+		&config,
+		&encryption_key,
+		&event_store,
+		&search_store,
+	)
 }
 
 #[tauri::command]
@@ -88,7 +237,536 @@ pub fn load_events(
 	app_config: State<AppConfig>,
 	encryption_key: State<EncryptionKey>,
 	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let events = storage::load_events(&config, &encryption_key, &event_store)?;
+	*search_store.index.lock().unwrap() = SearchIndex::build(&materialize(&events));
+	Ok(events)
+}
+
+#[tauri::command]
+pub fn replica_status(
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<Vec<ReplicaStatus>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	Ok(replica::get_replica_statuses(
+		&config,
+		&encryption_key,
+		&event_store,
+	))
+}
+
+#[tauri::command]
+pub fn resync_replica(
+	path: PathBuf,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<(), TasksError> {
+	let config = app_config.config.lock().unwrap();
+	replica::resync_replica(&config, path, &encryption_key, &event_store)
+}
+
+#[tauri::command]
+pub fn due_groups(event_store: State<EventStore>) -> BTreeMap<DueGroup, Vec<TaskId>> {
+	group_tasks_by_due(event_store.tasks().values(), &Local::now())
+}
+
+#[tauri::command]
+pub fn list_tasks_by_tag(tag: &str, event_store: State<EventStore>) -> Vec<Task> {
+	get_tasks_with_tag(&event_store.tasks(), tag)
+}
+
+#[tauri::command]
+pub fn list_tasks_by_project(project: &str, event_store: State<EventStore>) -> Vec<Task> {
+	get_tasks_in_project(&event_store.tasks(), project)
+}
+
+#[tauri::command]
+pub fn list_tasks_by_priority(priority: Priority, event_store: State<EventStore>) -> Vec<Task> {
+	get_tasks_with_priority(&event_store.tasks(), priority)
+}
+
+#[tauri::command]
+pub fn list_tags(event_store: State<EventStore>) -> Vec<String> {
+	get_all_tags(&event_store.tasks())
+}
+
+/// Returns the saved events so the frontend can apply them.
+#[tauri::command]
+pub fn rename_tag(
+	from: &str,
+	to: &str,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let first_id = get_next_event_id(&event_store.events.lock().unwrap());
+	let events = create_rename_tag_events(&event_store.tasks(), from, to, first_id);
+	save_generated_events(
+		events,
+		&config,
+		&encryption_key,
+		&event_store,
+		&search_store,
+	)
+}
+
+#[tauri::command]
+pub fn list_subtasks(task_id: TaskId, event_store: State<EventStore>) -> Vec<Task> {
+	get_subtasks(&event_store.tasks(), task_id)
+}
+
+/// Only includes tasks which have subtasks or checklist items.
+#[tauri::command]
+pub fn task_progress(event_store: State<EventStore>) -> BTreeMap<TaskId, Progress> {
+	let tasks = event_store.tasks();
+	tasks
+		.values()
+		.map(|task| (task.id, get_progress(&tasks, task)))
+		.filter(|(_, progress)| progress.total > 0)
+		.collect()
+}
+
+#[tauri::command]
+pub fn blocked_tasks(event_store: State<EventStore>) -> Vec<TaskId> {
+	get_blocked_task_ids(&event_store.tasks())
+}
+
+#[tauri::command]
+pub fn next_actionable_tasks(event_store: State<EventStore>) -> Vec<Task> {
+	get_next_actionable_tasks(&event_store.tasks())
+}
+
+/// The returned attachment still needs adding to the task with an `UpdateTask` event.
+#[tauri::command]
+pub fn add_attachment(
+	name: &str,
+	data: Vec<u8>,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+) -> Result<Attachment, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let _lock = storage::acquire_lock(&config.context)?;
+	attachment::save_attachment(&get_attachments_dirs(&config), name, &data, &encryption_key)
+}
+
+#[tauri::command]
+pub fn read_attachment(
+	hash: &str,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+) -> Result<Vec<u8>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	attachment::load_attachment(&get_attachments_dirs(&config), hash, &encryption_key)
+}
+
+#[tauri::command]
+pub fn collect_attachment_garbage(
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<Vec<String>, TasksError> {
+	// Without the key the file names can't be matched to the referenced hashes
+	if encryption_key.is_locked() {
+		return Err(TasksError::VaultError(
+			"Unlock the vault before collecting attachment garbage".to_string(),
+		));
+	}
+	let config = app_config.config.lock().unwrap();
+	let _lock = storage::acquire_lock(&config.context)?;
+	// Other devices' events may reference attachments this one hasn't loaded yet,
+	// and archived tasks keep their attachments
+	let mut events = storage::merge_all_replicas(&config, &encryption_key, &event_store)?;
+	events.extend(archive::load_archive(&config, &encryption_key)?);
+	attachment::collect_garbage(
+		&get_attachments_dirs(&config),
+		&attachment::get_referenced_hashes(&events),
+		&encryption_key,
+		SystemTime::now(),
+	)
+}
+
+fn save_new_event(
+	data: TaskEventData,
+	config: &Config,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<TaskEvent, TasksError> {
+	let event = TaskEvent {
+		id: get_next_event_id(&event_store.events.lock().unwrap()),
+		data,
+	};
+	storage::save_event(config, event.clone(), encryption_key, event_store)?;
+	Ok(event)
+}
+
+/// Stops any other running timer.
+#[tauri::command]
+pub fn start_timer(
+	task_id: TaskId,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<TaskEvent, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let timer = TimerEvent {
+		task_id,
+		at: Utc::now(),
+	};
+	save_new_event(
+		TaskEventData::StartTimer(timer),
+		&config,
+		&encryption_key,
+		&event_store,
+	)
+}
+
+#[tauri::command]
+pub fn stop_timer(
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<Option<TaskEvent>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let running = get_running_timer(&hashmap_to_sorted_vec(&event_store.events.lock().unwrap()));
+	running
+		.map(|timer| {
+			let timer = TimerEvent {
+				at: Utc::now(),
+				..timer
+			};
+			save_new_event(
+				TaskEventData::StopTimer(timer),
+				&config,
+				&encryption_key,
+				&event_store,
+			)
+		})
+		.transpose()
+}
+
+#[tauri::command]
+pub fn running_timer(event_store: State<EventStore>) -> Option<TimerEvent> {
+	get_running_timer(&hashmap_to_sorted_vec(&event_store.events.lock().unwrap()))
+}
+
+#[tauri::command]
+pub fn add_time_entry(
+	entry: TimeEntry,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<TaskEvent, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	save_new_event(
+		TaskEventData::AddTimeEntry(entry),
+		&config,
+		&encryption_key,
+		&event_store,
+	)
+}
+
+/// Covers whole local days from `from` to `to` inclusive.
+#[tauri::command]
+pub fn time_report(from: NaiveDate, to: NaiveDate, event_store: State<EventStore>) -> TimeReport {
+	let (from, to) = get_local_date_range(from, to);
+	let events = hashmap_to_sorted_vec(&event_store.events.lock().unwrap());
+	get_time_report(&materialize(&events), &events, from, to, Utc::now())
+}
+
+/// Seconds from starting each done task until finishing it.
+#[tauri::command]
+pub fn cycle_times(event_store: State<EventStore>) -> BTreeMap<TaskId, i64> {
+	event_store
+		.tasks()
+		.values()
+		.filter_map(|task| get_cycle_time(task).map(|duration| (task.id, duration.num_seconds())))
+		.collect()
+}
+
+/// Matching tasks, best matches first. See `parse_search_query` for the syntax.
+#[tauri::command]
+pub fn search_tasks(
+	query: &str,
+	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
+) -> Vec<Task> {
+	let task_ids = search_store
+		.index
+		.lock()
+		.unwrap()
+		.search(&parse_search_query(query));
+	let tasks = event_store.tasks();
+	task_ids
+		.iter()
+		.filter_map(|task_id| tasks.get(task_id).cloned())
+		.collect()
+}
+
+/// See `parse_filter` for the syntax.
+#[tauri::command]
+pub fn query_tasks(query: &str, event_store: State<EventStore>) -> Result<Vec<Task>, TasksError> {
+	let events = hashmap_to_sorted_vec(&event_store.events.lock().unwrap());
+	let filter = parse_filter(query, &materialize_fields(&events))?;
+	Ok(filter_tasks(&filter, &materialize(&events), &Local::now()))
+}
+
+#[tauri::command]
+pub fn list_views(event_store: State<EventStore>) -> Vec<View> {
+	materialize_views(&hashmap_to_sorted_vec(&event_store.events.lock().unwrap()))
+		.into_values()
+		.collect()
+}
+
+/// Creates the view, or replaces the one with the same id.
+#[tauri::command]
+pub fn save_view(
+	view: View,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<TaskEvent, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	save_new_event(
+		TaskEventData::SaveView(view),
+		&config,
+		&encryption_key,
+		&event_store,
+	)
+}
+
+#[tauri::command]
+pub fn delete_view(
+	view_id: ViewId,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<TaskEvent, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	save_new_event(
+		TaskEventData::DeleteView(view_id),
+		&config,
+		&encryption_key,
+		&event_store,
+	)
+}
+
+#[tauri::command]
+pub fn view_tasks(
+	view_id: ViewId,
+	event_store: State<EventStore>,
+) -> Result<Vec<Task>, TasksError> {
+	let events = hashmap_to_sorted_vec(&event_store.events.lock().unwrap());
+	let view = materialize_views(&events)
+		.remove(&view_id)
+		.ok_or_else(|| TasksError::UnknownError(format!("View not found: {}", view_id)))?;
+	evaluate_view(
+		&view,
+		&materialize(&events),
+		&materialize_fields(&events),
+		&Local::now(),
+	)
+}
+
+/// Returns the `ArchiveTask` events saved.
+#[tauri::command]
+pub fn archive_completed_tasks(
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
 ) -> Result<Vec<TaskEvent>, TasksError> {
 	let config = app_config.config.lock().unwrap();
-	storage::load_events(&config, &encryption_key, &event_store)
+	let events = archive::archive_tasks(&config, Utc::now(), &encryption_key, &event_store)?;
+	index_saved_events(&events, &event_store, &search_store);
+	Ok(events)
+}
+
+#[tauri::command]
+pub fn list_archived_tasks(
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+) -> Result<Vec<Task>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	Ok(archive::load_archived_tasks(&config, &encryption_key)?
+		.into_values()
+		.collect())
+}
+
+#[tauri::command]
+pub fn search_archived_tasks(
+	query: &str,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+) -> Result<Vec<Task>, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	archive::search_archived_tasks(query, &config, &encryption_key)
+}
+
+/// Returns the `CreateTask` event which brought the task back.
+#[tauri::command]
+pub fn restore_archived_task(
+	task_id: TaskId,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
+) -> Result<TaskEvent, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let event = archive::restore_task(&config, task_id, &encryption_key, &event_store)?;
+	index_saved_events(slice::from_ref(&event), &event_store, &search_store);
+	Ok(event)
+}
+
+#[tauri::command]
+pub fn list_trash(event_store: State<EventStore>) -> Vec<TrashedTask> {
+	let events = hashmap_to_sorted_vec(&event_store.events.lock().unwrap());
+	trash::get_trash(&events).into_values().collect()
+}
+
+/// Returns the `CreateTask` event which brought the task back.
+#[tauri::command]
+pub fn restore_deleted_task(
+	task_id: TaskId,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
+) -> Result<TaskEvent, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	let event = trash::restore_task(&config, task_id, &encryption_key, &event_store)?;
+	index_saved_events(slice::from_ref(&event), &event_store, &search_store);
+	Ok(event)
+}
+
+/// Renders unsaved details too, so the form can preview them.
+#[tauri::command]
+pub fn render_details(details: &str) -> RenderedDetails {
+	markdown::render_details(details)
+}
+
+#[tauri::command]
+pub fn list_links(event_store: State<EventStore>) -> Vec<TaskLink> {
+	get_all_links(&event_store.tasks())
+}
+
+#[tauri::command]
+pub fn list_fields(event_store: State<EventStore>) -> Vec<FieldDefinition> {
+	materialize_fields(&hashmap_to_sorted_vec(&event_store.events.lock().unwrap()))
+		.into_values()
+		.collect()
+}
+
+/// Creates the field, or replaces the one with the same id.
+#[tauri::command]
+pub fn save_field(
+	field: FieldDefinition,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<TaskEvent, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	save_new_event(
+		TaskEventData::SaveField(field),
+		&config,
+		&encryption_key,
+		&event_store,
+	)
+}
+
+/// Also removes the field's values from every task.
+#[tauri::command]
+pub fn delete_field(
+	field_id: FieldId,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+) -> Result<TaskEvent, TasksError> {
+	let config = app_config.config.lock().unwrap();
+	save_new_event(
+		TaskEventData::DeleteField(field_id),
+		&config,
+		&encryption_key,
+		&event_store,
+	)
+}
+
+#[tauri::command]
+pub fn list_vaults(app_context: State<AppContext>) -> VaultRegistry {
+	vault::load_registry(&app_context)
+}
+
+#[tauri::command]
+pub fn create_vault(name: &str, app_context: State<AppContext>) -> Result<Vault, TasksError> {
+	let mut registry = vault::load_registry(&app_context);
+	let vault = registry.create(Utc::now().timestamp_millis() as VaultId, name)?;
+	vault::save_registry(&app_context, &registry)?;
+	Ok(vault)
+}
+
+/// Lists the vaults other devices have synced here which can be opened with `open_vault`.
+#[tauri::command]
+pub fn find_vaults(app_context: State<AppContext>) -> BTreeSet<VaultId> {
+	vault::find_unregistered_vaults(&app_context, &vault::load_registry(&app_context))
+}
+
+#[tauri::command]
+pub fn open_vault(
+	vault_id: VaultId,
+	name: &str,
+	app_context: State<AppContext>,
+) -> Result<Vault, TasksError> {
+	vault::open_vault(
+		&app_context,
+		&mut vault::load_registry(&app_context),
+		vault_id,
+		name,
+	)
+}
+
+#[tauri::command]
+pub fn rename_vault(
+	vault_id: VaultId,
+	name: &str,
+	app_context: State<AppContext>,
+) -> Result<(), TasksError> {
+	let mut registry = vault::load_registry(&app_context);
+	registry.rename(vault_id, name)?;
+	vault::save_registry(&app_context, &registry)
+}
+
+/// Permanently deletes the vault's files from every location it syncs to.
+#[tauri::command]
+pub fn delete_vault(vault_id: VaultId, app_context: State<AppContext>) -> Result<(), TasksError> {
+	vault::delete_vault(
+		&app_context,
+		&mut vault::load_registry(&app_context),
+		vault_id,
+	)
+}
+
+/// Locks the current vault, so that only one is ever unlocked, and returns the config of the next one.
+#[tauri::command]
+pub fn switch_vault(
+	vault_id: VaultId,
+	app_context: State<AppContext>,
+	app_config: State<AppConfig>,
+	encryption_key: State<EncryptionKey>,
+	event_store: State<EventStore>,
+	search_store: State<SearchStore>,
+) -> Result<Config, TasksError> {
+	let mut registry = vault::load_registry(&app_context);
+	registry.activate(vault_id)?;
+	vault::save_registry(&app_context, &registry)?;
+
+	lock_vault(&encryption_key, &event_store, &search_store);
+	let mut config = app_config.config.lock().unwrap();
+	*config = storage::load_config(&app_context, vault_id);
+	Ok(config.clone())
 }
@@ -1,41 +1,104 @@
 use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use crate::context::AppContext;
+use crate::vault::{VaultId, DEFAULT_VAULT_ID};
+use crate::workflow::Workflow;
+
+#[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
+	/// Specified in minutes
 	pub auto_lock_timeout: u32,
 	pub icloud_enabled: bool,
 	pub dropbox_enabled: bool,
+	pub workflow: Workflow,
+	/// Closed tasks are archived this many days after their last status change
+	pub archive_after_days: u32,
+	/// Deleted tasks are purged this many days after deletion
+	pub trash_retention_days: u32,
+	/// Each vault keeps its own config, so this is known from where it was loaded
+	#[serde(skip)]
+	pub vault_id: VaultId,
+	/// Resolved at startup rather than saved with the rest
+	#[serde(skip)]
+	pub context: AppContext,
 }
 
-impl Default for Config {
-	fn default() -> Self {
+/// The config as saved or as sent by the frontend, which doesn't say which vault or context it applies to.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigData {
+	pub auto_lock_timeout: u32,
+	pub icloud_enabled: bool,
+	pub dropbox_enabled: bool,
+	#[serde(default)]
+	pub workflow: Workflow,
+	#[serde(default = "default_archive_after_days")]
+	pub archive_after_days: u32,
+	#[serde(default = "default_trash_retention_days")]
+	pub trash_retention_days: u32,
+}
+
+impl ConfigData {
+	pub fn into_config(self, context: &AppContext, vault_id: VaultId) -> Config {
+		Config {
+			auto_lock_timeout: self.auto_lock_timeout,
+			icloud_enabled: self.icloud_enabled,
+			dropbox_enabled: self.dropbox_enabled,
+			workflow: self.workflow,
+			archive_after_days: self.archive_after_days,
+			trash_retention_days: self.trash_retention_days,
+			vault_id,
+			context: context.clone(),
+		}
+	}
+}
+
+fn default_archive_after_days() -> u32 {
+	30
+}
+
+fn default_trash_retention_days() -> u32 {
+	30
+}
+
+impl Config {
+	/// The defaults for the default vault. Takes the context rather than being `Default`,
+	/// since there is no sensible default for where the files are.
+	pub fn new(context: &AppContext) -> Self {
 		Config {
-			/** Specified in minutes */
 			auto_lock_timeout: 10,
 			icloud_enabled: false,
 			dropbox_enabled: false,
+			workflow: Default::default(),
+			archive_after_days: default_archive_after_days(),
+			trash_retention_days: default_trash_retention_days(),
+			vault_id: DEFAULT_VAULT_ID,
+			context: context.clone(),
 		}
 	}
 }
 
-#[derive(Default)]
 pub struct AppConfig {
 	pub config: Mutex<Config>,
 }
 
 impl AppConfig {
-	pub fn new() -> Self {
-		Default::default()
+	pub fn new(context: &AppContext) -> Self {
+		AppConfig {
+			config: Mutex::new(Config::new(context)),
+		}
 	}
 }
 
-pub const SERIALIZATION_VERSION: &str = "1";
+pub const SERIALIZATION_VERSION: u32 = 13;
 
 pub const SHUSHING_FACE_DIRNAME: &str = ".shushing-face";
 pub const SALT_FILENAME: &str = "salt";
 pub const CONFIG_FILENAME: &str = "config.json";
+pub const LOCK_FILENAME: &str = "lock";
+pub const FIRED_REMINDERS_FILENAME: &str = "reminders";
 // Nested under home dir
 pub const ICLOUD_DIRNAME: &str = "Library/Mobile Documents/com~apple~CloudDocs";
 // Dropbox config: ~/.dropbox/info.json
@@ -43,3 +106,10 @@ pub const ICLOUD_DIRNAME: &str = "Library/Mobile Documents/com~apple~CloudDocs";
 pub const DROPBOX_DIRNAME: &str = "Library/CloudStorage/Dropbox";
 
 pub const TASKS_FILENAME: &str = "tasks";
+pub const ATTACHMENTS_DIRNAME: &str = "attachments";
+pub const ARCHIVE_FILENAME: &str = "archive";
+/// Nested under the default vault's directory
+pub const VAULTS_DIRNAME: &str = "vaults";
+pub const REGISTRY_FILENAME: &str = "vaults.json";
+/// Next to the executable
+pub const PORTABLE_FILENAME: &str = "portable";
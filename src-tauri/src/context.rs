@@ -0,0 +1,139 @@
+use std::env::{args, current_exe, var_os};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use home::home_dir;
+
+use crate::config::PORTABLE_FILENAME;
+use crate::error::TasksError;
+
+pub const DATA_DIR_FLAG: &str = "--data-dir";
+
+pub const DATA_DIR_ENV_VAR: &str = "SHUSHING_FACE_HOME";
+
+/// Where the app keeps its files, resolved once at startup and used by every path helper.
+/// Deliberately not `Default`, since empty paths would resolve against the working directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppContext {
+	/// Holds the local vault directory, in place of the home directory
+	pub root_dir: PathBuf,
+	/// Holds the synced folders, which are wherever their clients put them
+	pub home_dir: PathBuf,
+}
+
+impl AppContext {
+	/// Keeps everything, synced folders included, inside the given directory.
+	pub fn new(root_dir: &Path) -> Self {
+		AppContext {
+			root_dir: root_dir.to_path_buf(),
+			home_dir: root_dir.to_path_buf(),
+		}
+	}
+
+	pub fn from_environment() -> Result<Self, TasksError> {
+		let home_dir = home_dir()
+			.ok_or_else(|| TasksError::UnknownError("Failed to get home directory".to_string()))?;
+		let exe_dir = current_exe()
+			.ok()
+			.and_then(|exe| exe.parent().map(Path::to_path_buf));
+		let root_dir = resolve_root_dir(args().skip(1), var_os(DATA_DIR_ENV_VAR), exe_dir)
+			.unwrap_or_else(|| home_dir.clone());
+		Ok(AppContext { root_dir, home_dir })
+	}
+}
+
+/// Accepts both `--data-dir <dir>` and `--data-dir=<dir>`.
+fn get_flag_dir(args: impl IntoIterator<Item = String>) -> Option<PathBuf> {
+	let mut args = args.into_iter();
+	while let Some(arg) = args.next() {
+		if arg == DATA_DIR_FLAG {
+			return args.next().map(PathBuf::from);
+		}
+		if let Some(dir) = arg
+			.strip_prefix(DATA_DIR_FLAG)
+			.and_then(|rest| rest.strip_prefix('='))
+		{
+			return Some(PathBuf::from(dir));
+		}
+	}
+	None
+}
+
+/// A `portable` file next to the executable keeps the data beside it, eg on a USB stick.
+fn get_portable_dir(exe_dir: Option<PathBuf>) -> Option<PathBuf> {
+	exe_dir.filter(|dir| dir.join(PORTABLE_FILENAME).exists())
+}
+
+/// The command line flag wins over the environment variable, which wins over portable mode.
+/// None means the home directory.
+pub fn resolve_root_dir(
+	args: impl IntoIterator<Item = String>,
+	env_dir: Option<OsString>,
+	exe_dir: Option<PathBuf>,
+) -> Option<PathBuf> {
+	get_flag_dir(args)
+		.or_else(|| env_dir.filter(|dir| !dir.is_empty()).map(PathBuf::from))
+		.or_else(|| get_portable_dir(exe_dir))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs::File;
+	use tempfile::tempdir;
+
+	fn to_args(args: &[&str]) -> Vec<String> {
+		args.iter().map(|arg| arg.to_string()).collect()
+	}
+
+	#[test]
+	fn test_get_flag_dir() {
+		assert_eq!(
+			get_flag_dir(to_args(&["--data-dir", "/tmp/tasks"])),
+			Some(PathBuf::from("/tmp/tasks"))
+		);
+		assert_eq!(
+			get_flag_dir(to_args(&["--verbose", "--data-dir=/tmp/tasks"])),
+			Some(PathBuf::from("/tmp/tasks"))
+		);
+		assert_eq!(get_flag_dir(to_args(&["--data-dir"])), None);
+		assert_eq!(get_flag_dir(to_args(&["--data-directory=/tmp"])), None);
+	}
+
+	#[test]
+	fn test_resolve_root_dir() {
+		let exe_dir = tempdir().unwrap();
+		let exe_path = exe_dir.path().to_path_buf();
+
+		assert_eq!(
+			resolve_root_dir(Vec::new(), None, Some(exe_path.clone())),
+			None
+		);
+		assert_eq!(
+			resolve_root_dir(Vec::new(), Some(OsString::new()), None),
+			None
+		);
+
+		File::create(exe_dir.path().join(PORTABLE_FILENAME)).unwrap();
+		assert_eq!(
+			resolve_root_dir(Vec::new(), None, Some(exe_path.clone())),
+			Some(exe_path.clone())
+		);
+		assert_eq!(
+			resolve_root_dir(
+				Vec::new(),
+				Some(OsString::from("/tmp/env")),
+				Some(exe_path.clone())
+			),
+			Some(PathBuf::from("/tmp/env"))
+		);
+		assert_eq!(
+			resolve_root_dir(
+				to_args(&["--data-dir", "/tmp/flag"]),
+				Some(OsString::from("/tmp/env")),
+				Some(exe_path)
+			),
+			Some(PathBuf::from("/tmp/flag"))
+		);
+	}
+}
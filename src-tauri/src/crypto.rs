@@ -3,7 +3,9 @@ use aes_gcm::{
 	Aes256Gcm,
 };
 use argon2::Argon2;
+use hmac::{Hmac, Mac};
 use rand::{thread_rng, Rng};
+use sha2::Sha256;
 use std::sync::Mutex;
 
 use crate::error::TasksError;
@@ -15,6 +17,13 @@ pub const NONCE_SIZE: usize = 12;
 #[derive(Default)]
 pub struct EncryptionKey(pub Mutex<[u8; ENCRYPTION_KEY_SIZE]>);
 
+impl EncryptionKey {
+	/// The key is zeroed until a password is entered and again on lock.
+	pub fn is_locked(&self) -> bool {
+		self.0.lock().unwrap().iter().all(|byte| *byte == 0)
+	}
+}
+
 pub type Salt = [u8; SALT_SIZE];
 
 pub fn generate_random_bytes(buf: &mut [u8]) -> () {
@@ -49,6 +58,14 @@ pub fn decrypt(
 	encrypted_data: &[u8],
 	key: &[u8; ENCRYPTION_KEY_SIZE],
 ) -> Result<String, TasksError> {
+	Ok(String::from_utf8(decrypt_bytes(encrypted_data, key)?)?)
+}
+
+/// Like `decrypt`, for binary data such as attachments.
+pub fn decrypt_bytes(
+	encrypted_data: &[u8],
+	key: &[u8; ENCRYPTION_KEY_SIZE],
+) -> Result<Vec<u8>, TasksError> {
 	if encrypted_data.len() < NONCE_SIZE {
 		return Err(TasksError::CryptoError(
 			"Encrypted data is too short".into(),
@@ -58,8 +75,15 @@ pub fn decrypt(
 	let (nonce, ciphertext) = encrypted_data.split_at(NONCE_SIZE);
 	let cipher = Aes256Gcm::new_from_slice(key)?;
 
-	let decrypted_data = cipher.decrypt(nonce.into(), ciphertext)?;
-	Ok(String::from_utf8(decrypted_data)?)
+	Ok(cipher.decrypt(nonce.into(), ciphertext)?)
+}
+
+/// HMAC-SHA256 of the data under the key.
+pub fn keyed_hash(key: &[u8; ENCRYPTION_KEY_SIZE], data: &[u8]) -> [u8; 32] {
+	let mut mac =
+		<Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any size");
+	mac.update(data);
+	mac.finalize().into_bytes().into()
 }
 
 #[cfg(test)]
@@ -110,6 +134,45 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_encrypt_decrypt_bytes() {
+		let encryption_key = [7u8; ENCRYPTION_KEY_SIZE];
+		let data = [0u8, 159, 146, 150, 255];
+		let encrypted_data = encrypt(&data, &encryption_key).expect("Encryption should succeed.");
+		assert_eq!(
+			decrypt_bytes(&encrypted_data, &encryption_key).expect("Decryption should succeed."),
+			data
+		);
+		assert!(
+			decrypt(&encrypted_data, &encryption_key).is_err(),
+			"Invalid UTF-8 should not decrypt to a string."
+		);
+	}
+
+	#[test]
+	fn test_keyed_hash() {
+		let hash = keyed_hash(&[7u8; ENCRYPTION_KEY_SIZE], b"hello");
+		assert_eq!(
+			crate::util::to_hex_string(&hash),
+			"290af183d08286ae740dfed386724985dc666de6350a8df2e8520307ae2503ed"
+		);
+		assert_ne!(keyed_hash(&[8u8; ENCRYPTION_KEY_SIZE], b"hello"), hash);
+	}
+
+	#[test]
+	fn test_encryption_key_is_locked() {
+		let encryption_key = EncryptionKey::default();
+		assert!(encryption_key.is_locked());
+
+		derive_key(
+			"strong_password",
+			&[1u8; SALT_SIZE],
+			&mut encryption_key.0.lock().unwrap(),
+		)
+		.expect("Key derivation should succeed.");
+		assert!(!encryption_key.is_locked());
+	}
+
 	#[test]
 	fn test_decrypt_with_invalid_data() {
 		let encryption_key = [0u8; ENCRYPTION_KEY_SIZE];
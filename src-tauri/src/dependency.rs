@@ -0,0 +1,161 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::reducer::Tasks;
+use crate::task::{Task, TaskId};
+
+/// Whether `task_id` depending on `depends_on` would make it a prerequisite of itself.
+pub fn would_create_dependency_cycle(
+	tasks: &Tasks,
+	task_id: TaskId,
+	depends_on: &[TaskId],
+) -> bool {
+	let mut visited = HashSet::new();
+	let mut pending: Vec<TaskId> = depends_on.to_vec();
+	while let Some(id) = pending.pop() {
+		if id == task_id {
+			return true;
+		}
+		if visited.insert(id) {
+			if let Some(task) = tasks.get(&id) {
+				pending.extend(&task.depends_on);
+			}
+		}
+	}
+	false
+}
+
+/// Prerequisites which are closed or no longer exist do not block.
+pub fn is_blocked(tasks: &Tasks, task: &Task) -> bool {
+	task.depends_on
+		.iter()
+		.filter_map(|id| tasks.get(id))
+		.any(|prerequisite| !prerequisite.status.is_closed())
+}
+
+pub fn get_blocked_task_ids(tasks: &Tasks) -> Vec<TaskId> {
+	tasks
+		.values()
+		.filter(|task| !task.status.is_closed() && is_blocked(tasks, task))
+		.map(|task| task.id)
+		.collect()
+}
+
+/// Orders open tasks so that every task comes after its prerequisites, preferring higher priorities.
+pub fn get_topological_order(tasks: &Tasks) -> Vec<TaskId> {
+	let open_tasks: BTreeMap<TaskId, &Task> = tasks
+		.values()
+		.filter(|task| !task.status.is_closed())
+		.map(|task| (task.id, task))
+		.collect();
+
+	let mut remaining_prerequisites: BTreeMap<TaskId, usize> = BTreeMap::new();
+	let mut dependents: BTreeMap<TaskId, Vec<TaskId>> = BTreeMap::new();
+	for task in open_tasks.values() {
+		let prerequisites: BTreeSet<TaskId> = task
+			.depends_on
+			.iter()
+			.filter(|id| open_tasks.contains_key(id))
+			.cloned()
+			.collect();
+		for prerequisite in &prerequisites {
+			dependents.entry(*prerequisite).or_default().push(task.id);
+		}
+		remaining_prerequisites.insert(task.id, prerequisites.len());
+	}
+
+	let mut ready: BTreeSet<(Reverse<_>, TaskId)> = open_tasks
+		.values()
+		.filter(|task| remaining_prerequisites[&task.id] == 0)
+		.map(|task| (Reverse(task.priority), task.id))
+		.collect();
+	let mut order = Vec::new();
+	while let Some(next) = ready.iter().next().cloned() {
+		ready.remove(&next);
+		let (_, id) = next;
+		order.push(id);
+		for dependent in dependents.get(&id).into_iter().flatten() {
+			let remaining = remaining_prerequisites.get_mut(dependent).unwrap();
+			*remaining -= 1;
+			if *remaining == 0 {
+				ready.insert((Reverse(open_tasks[dependent].priority), *dependent));
+			}
+		}
+	}
+	order
+}
+
+/// Open tasks which are not blocked, in topological order.
+pub fn get_next_actionable_tasks(tasks: &Tasks) -> Vec<Task> {
+	get_topological_order(tasks)
+		.into_iter()
+		.map(|id| &tasks[&id])
+		.filter(|task| !is_blocked(tasks, task))
+		.cloned()
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::task::Priority;
+	use crate::workflow::Status;
+
+	fn create_task(id: TaskId, depends_on: Vec<TaskId>, priority: Priority) -> Task {
+		Task {
+			id,
+			depends_on,
+			priority,
+			..Default::default()
+		}
+	}
+
+	fn create_tasks() -> Tasks {
+		// 1 <- 2 <- 4, 3 <- 4
+		Tasks::from([
+			(1, create_task(1, vec![], Priority::Normal)),
+			(2, create_task(2, vec![1], Priority::Urgent)),
+			(3, create_task(3, vec![], Priority::Low)),
+			(4, create_task(4, vec![2, 3], Priority::High)),
+		])
+	}
+
+	#[test]
+	fn test_would_create_dependency_cycle() {
+		let tasks = create_tasks();
+
+		assert!(would_create_dependency_cycle(&tasks, 1, &[4]));
+		assert!(would_create_dependency_cycle(&tasks, 1, &[1]));
+		assert!(!would_create_dependency_cycle(&tasks, 3, &[1, 2]));
+		assert!(!would_create_dependency_cycle(&tasks, 4, &[9]));
+	}
+
+	#[test]
+	fn test_get_blocked_task_ids() {
+		let mut tasks = create_tasks();
+		assert_eq!(get_blocked_task_ids(&tasks), vec![2, 4]);
+
+		tasks.get_mut(&1).unwrap().status = Status::Done;
+		assert_eq!(get_blocked_task_ids(&tasks), vec![4]);
+	}
+
+	#[test]
+	fn test_get_topological_order() {
+		let mut tasks = create_tasks();
+		assert_eq!(get_topological_order(&tasks), vec![1, 2, 3, 4]);
+
+		tasks.get_mut(&1).unwrap().status = Status::Done;
+		assert_eq!(get_topological_order(&tasks), vec![2, 3, 4]);
+	}
+
+	#[test]
+	fn test_get_next_actionable_tasks() {
+		let tasks = create_tasks();
+
+		let ids: Vec<TaskId> = get_next_actionable_tasks(&tasks)
+			.iter()
+			.map(|task| task.id)
+			.collect();
+		assert_eq!(ids, vec![1, 3]);
+	}
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone};
+use serde::Serialize;
+
+use crate::task::{Deadline, Task, TaskId};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DueGroup {
+	Overdue,
+	Today,
+	/// After today but no later than Sunday
+	ThisWeek,
+	Later,
+	Someday,
+}
+
+fn get_end_of_week(date: NaiveDate) -> NaiveDate {
+	date + Duration::days(6 - date.weekday().num_days_from_monday() as i64)
+}
+
+/// Dates are compared in the time zone of `now`, which should be the user's.
+pub fn get_due_group<Z: TimeZone>(deadline: Option<&Deadline>, now: &DateTime<Z>) -> DueGroup {
+	let today = now.date_naive();
+	let (due_date, is_overdue) = match deadline {
+		None => return DueGroup::Someday,
+		Some(Deadline::Date(date)) => (*date, *date < today),
+		Some(Deadline::Zoned(zoned)) => {
+			let due = zoned.to_datetime().with_timezone(&now.timezone());
+			(due.date_naive(), due < *now)
+		}
+	};
+
+	if is_overdue {
+		DueGroup::Overdue
+	} else if due_date == today {
+		DueGroup::Today
+	} else if due_date <= get_end_of_week(today) {
+		DueGroup::ThisWeek
+	} else {
+		DueGroup::Later
+	}
+}
+
+/// The due date in the time zone of `now`.
+pub fn get_due_date<Z: TimeZone>(deadline: &Deadline, now: &DateTime<Z>) -> NaiveDate {
+	match deadline {
+		Deadline::Date(date) => *date,
+		Deadline::Zoned(zoned) => zoned
+			.to_datetime()
+			.with_timezone(&now.timezone())
+			.date_naive(),
+	}
+}
+
+pub fn is_overdue<Z: TimeZone>(task: &Task, now: &DateTime<Z>) -> bool {
+	!task.status.is_closed() && get_due_group(task.deadline.as_ref(), now) == DueGroup::Overdue
+}
+
+/// Closed tasks are left out.
+pub fn group_tasks_by_due<'a, Z: TimeZone>(
+	tasks: impl IntoIterator<Item = &'a Task>,
+	now: &DateTime<Z>,
+) -> BTreeMap<DueGroup, Vec<TaskId>> {
+	let mut groups: BTreeMap<DueGroup, Vec<TaskId>> = BTreeMap::new();
+	for task in tasks.into_iter().filter(|task| !task.status.is_closed()) {
+		groups
+			.entry(get_due_group(task.deadline.as_ref(), now))
+			.or_default()
+			.push(task.id);
+	}
+	groups
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::task::ZonedDateTime;
+	use crate::workflow::Status;
+	use chrono_tz::{America, Asia, Tz};
+
+	/// Wednesday at 9pm in New York, which is already Thursday in Tokyo
+	fn get_now() -> DateTime<Tz> {
+		America::New_York
+			.with_ymd_and_hms(2026, 10, 21, 21, 0, 0)
+			.unwrap()
+	}
+
+	fn date(date: &str) -> Deadline {
+		Deadline::Date(date.parse().unwrap())
+	}
+
+	fn zoned(local: &str, time_zone: Tz) -> Deadline {
+		Deadline::Zoned(ZonedDateTime {
+			local: local.parse().unwrap(),
+			time_zone,
+		})
+	}
+
+	#[test]
+	fn test_get_due_group_for_dates() {
+		let now = get_now();
+		let cases = [
+			(None, DueGroup::Someday),
+			(Some(date("2026-10-20")), DueGroup::Overdue),
+			(Some(date("2026-10-21")), DueGroup::Today),
+			(Some(date("2026-10-25")), DueGroup::ThisWeek),
+			(Some(date("2026-10-26")), DueGroup::Later),
+		];
+		for (deadline, expected) in cases {
+			assert_eq!(get_due_group(deadline.as_ref(), &now), expected);
+		}
+	}
+
+	#[test]
+	fn test_get_due_group_for_zoned_date_times() {
+		let now = get_now();
+		let cases = [
+			(
+				zoned("2026-10-21T20:00:00", America::New_York),
+				DueGroup::Overdue,
+			),
+			(
+				zoned("2026-10-21T23:00:00", America::New_York),
+				DueGroup::Today,
+			),
+			// 11am Thursday in Tokyo is still Wednesday evening in New York
+			(zoned("2026-10-22T11:00:00", Asia::Tokyo), DueGroup::Today),
+			(
+				zoned("2026-10-22T15:00:00", Asia::Tokyo),
+				DueGroup::ThisWeek,
+			),
+		];
+		for (deadline, expected) in cases {
+			assert_eq!(get_due_group(Some(&deadline), &now), expected);
+		}
+	}
+
+	#[test]
+	fn test_group_tasks_by_due() {
+		let now = get_now();
+		let create_task = |id: TaskId, deadline: Option<Deadline>, status: Status| Task {
+			id,
+			description: Default::default(),
+			deadline,
+			details: Default::default(),
+			status,
+			..Default::default()
+		};
+		let tasks = vec![
+			create_task(1, Some(date("2026-10-01")), Status::Todo),
+			create_task(2, Some(date("2026-10-01")), Status::Done),
+			create_task(3, None, Status::Waiting),
+			create_task(4, Some(date("2026-10-21")), Status::InProgress),
+			create_task(5, Some(date("2026-10-21")), Status::Cancelled),
+		];
+
+		let groups = group_tasks_by_due(&tasks, &now);
+
+		assert_eq!(
+			groups,
+			BTreeMap::from([
+				(DueGroup::Overdue, vec![1]),
+				(DueGroup::Today, vec![4]),
+				(DueGroup::Someday, vec![3]),
+			])
+		);
+		assert!(is_overdue(&tasks[0], &now));
+		assert!(!is_overdue(&tasks[1], &now));
+	}
+}
@@ -12,6 +12,11 @@ pub enum TasksError {
 	AesGcmError(aes_gcm::Error),
 	AesGcmInvalidLengthError(aes_gcm::aes::cipher::InvalidLength),
 	Utf8Error(std::string::FromUtf8Error),
+	VaultLockedError(String),
+	VersionError(String),
+	InvalidEventError(String),
+	QueryError(String),
+	VaultError(String),
 }
 
 impl fmt::Display for TasksError {
@@ -28,6 +33,13 @@ impl fmt::Display for TasksError {
 				write!(f, "AES-GCM invalid length error: {}", e)
 			}
 			TasksError::Utf8Error(e) => write!(f, "UTF8 error: {}", e),
+			TasksError::VaultLockedError(e) => {
+				write!(f, "Vault is in use by another instance: {}", e)
+			}
+			TasksError::VersionError(e) => write!(f, "Version error: {}", e),
+			TasksError::InvalidEventError(e) => write!(f, "Invalid event: {}", e),
+			TasksError::QueryError(e) => write!(f, "Invalid query: {}", e),
+			TasksError::VaultError(e) => write!(f, "Vault error: {}", e),
 		}
 	}
 }
@@ -1,8 +1,13 @@
+use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Mutex;
 
+use crate::field::{FieldDefinition, FieldId};
+use crate::reducer::{materialize, Tasks};
 use crate::task::{Task, TaskId};
+use crate::timetracking::{TimeEntry, TimerEvent};
+use crate::view::{View, ViewId};
 
 pub type EventId = u64;
 
@@ -11,6 +16,15 @@ pub enum TaskEventData {
 	CreateTask(Task),
 	UpdateTask(Task),
 	DeleteTask(TaskId),
+	StartTimer(TimerEvent),
+	StopTimer(TimerEvent),
+	AddTimeEntry(TimeEntry),
+	SaveView(View),
+	DeleteView(ViewId),
+	/// Marks where the task's events were moved to the archive
+	ArchiveTask(TaskId),
+	SaveField(FieldDefinition),
+	DeleteField(FieldId),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -22,14 +36,22 @@ pub struct TaskEvent {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EventStore {
 	pub events: Mutex<HashMap<EventId, TaskEvent>>,
+	/// Merged in from replicas while saving, for the watcher to announce as if it had found them
+	#[serde(skip)]
+	pub unannounced: Mutex<Vec<TaskEvent>>,
 }
 
 impl EventStore {
 	pub fn new() -> Self {
 		EventStore {
 			events: Mutex::new(HashMap::new()),
+			unannounced: Mutex::new(Vec::new()),
 		}
 	}
+
+	pub fn tasks(&self) -> Tasks {
+		materialize(&hashmap_to_sorted_vec(&self.events.lock().unwrap()))
+	}
 }
 
 pub fn hashmap_to_sorted_vec(hashmap: &HashMap<EventId, TaskEvent>) -> Vec<TaskEvent> {
@@ -38,9 +60,69 @@ pub fn hashmap_to_sorted_vec(hashmap: &HashMap<EventId, TaskEvent>) -> Vec<TaskE
 	events
 }
 
+/// Event ids are creation timestamps in milliseconds, bumped past existing ids if the clock is behind.
+pub fn get_next_event_id(hashmap: &HashMap<EventId, TaskEvent>) -> EventId {
+	let now = Utc::now().timestamp_millis() as EventId;
+	hashmap
+		.keys()
+		.max()
+		.map_or(now, |last_id| now.max(last_id + 1))
+}
+
+/// Like `get_next_event_id`, for events which are already sorted.
+pub fn get_next_event_id_after(sorted_events: &[TaskEvent]) -> EventId {
+	let now = Utc::now().timestamp_millis() as EventId;
+	sorted_events
+		.last()
+		.map_or(now, |last| now.max(last.id + 1))
+}
+
+pub fn get_event_time(event_id: EventId) -> DateTime<Utc> {
+	Utc.timestamp_millis_opt(event_id as i64)
+		.single()
+		.unwrap_or_else(Utc::now)
+}
+
+/// Inserts any events not already present and returns the newly added ones in order.
+pub fn merge_events(
+	hashmap: &mut HashMap<EventId, TaskEvent>,
+	incoming: Vec<TaskEvent>,
+) -> Vec<TaskEvent> {
+	let mut added: Vec<TaskEvent> = incoming
+		.into_iter()
+		.filter(|event| !hashmap.contains_key(&event.id))
+		.collect();
+	added.sort();
+	added.dedup_by_key(|event| event.id);
+	for event in &added {
+		hashmap.insert(event.id, event.clone());
+	}
+	added
+}
+
+fn get_event_task_id(event: &TaskEvent) -> Option<TaskId> {
+	match &event.data {
+		TaskEventData::CreateTask(task) | TaskEventData::UpdateTask(task) => Some(task.id),
+		_ => None,
+	}
+}
+
+/// Splits off the events which hold the contents of the tasks. Deletions and other events
+/// referring to the tasks stay behind, so copies of the removed events merged back in from
+/// stale replicas are still overridden.
+pub fn split_task_events(
+	events: Vec<TaskEvent>,
+	task_ids: &BTreeSet<TaskId>,
+) -> (Vec<TaskEvent>, Vec<TaskEvent>) {
+	events.into_iter().partition(|event| {
+		!get_event_task_id(event).map_or(false, |task_id| task_ids.contains(&task_id))
+	})
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::workflow::Status;
 
 	#[test]
 	fn test_event_store_new() {
@@ -56,7 +138,8 @@ mod tests {
 			description: "Test Task".to_string(),
 			deadline: Default::default(),
 			details: Default::default(),
-			completed: false,
+			status: Status::Todo,
+			..Default::default()
 		};
 
 		let event = TaskEvent {
@@ -83,14 +166,16 @@ mod tests {
 			description: "Task 1".to_string(),
 			deadline: Default::default(),
 			details: Default::default(),
-			completed: false,
+			status: Status::Todo,
+			..Default::default()
 		};
 		let task2 = Task {
 			id: 2,
 			description: "Task 2".to_string(),
 			deadline: Default::default(),
 			details: Default::default(),
-			completed: false,
+			status: Status::Todo,
+			..Default::default()
 		};
 
 		let event1 = TaskEvent {
@@ -111,4 +196,81 @@ mod tests {
 		assert_eq!(sorted_events[0].id, 1);
 		assert_eq!(sorted_events[1].id, 2);
 	}
+
+	#[test]
+	fn test_get_next_event_id() {
+		let before = Utc::now().timestamp_millis() as EventId;
+		assert!(get_next_event_id(&HashMap::new()) >= before);
+
+		let future_id = before + 60_000;
+		let mut hashmap = HashMap::new();
+		hashmap.insert(
+			future_id,
+			TaskEvent {
+				id: future_id,
+				data: TaskEventData::DeleteTask(1),
+			},
+		);
+		assert_eq!(get_next_event_id(&hashmap), future_id + 1);
+	}
+
+	#[test]
+	fn test_merge_events() {
+		let task = Task {
+			id: 1,
+			description: "Task 1".to_string(),
+			deadline: Default::default(),
+			details: Default::default(),
+			status: Status::Todo,
+			..Default::default()
+		};
+		let existing = TaskEvent {
+			id: 1,
+			data: TaskEventData::CreateTask(task.clone()),
+		};
+		let incoming = TaskEvent {
+			id: 2,
+			data: TaskEventData::UpdateTask(Task {
+				status: Status::Done,
+				..task
+			}),
+		};
+
+		let mut hashmap = HashMap::new();
+		hashmap.insert(existing.id, existing.clone());
+
+		let added = merge_events(&mut hashmap, vec![existing, incoming.clone()]);
+
+		assert_eq!(added, vec![incoming]);
+		assert_eq!(hashmap.len(), 2);
+		assert!(merge_events(&mut hashmap, added).is_empty());
+	}
+
+	#[test]
+	fn test_split_task_events() {
+		let create_task = |id: TaskId| Task {
+			id,
+			..Default::default()
+		};
+		let create = TaskEvent {
+			id: 1,
+			data: TaskEventData::CreateTask(create_task(2)),
+		};
+		let update = TaskEvent {
+			id: 2,
+			data: TaskEventData::UpdateTask(create_task(1)),
+		};
+		let delete = TaskEvent {
+			id: 3,
+			data: TaskEventData::DeleteTask(1),
+		};
+
+		let (kept, split) = split_task_events(
+			vec![create.clone(), update.clone(), delete.clone()],
+			&BTreeSet::from([1]),
+		);
+
+		assert_eq!(kept, vec![create, delete]);
+		assert_eq!(split, vec![update]);
+	}
 }
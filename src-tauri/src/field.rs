@@ -0,0 +1,301 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::error::TasksError;
+use crate::event::{TaskEvent, TaskEventData};
+use crate::reducer::Tasks;
+use crate::task::Task;
+
+pub type FieldId = u64;
+
+pub type Fields = BTreeMap<FieldId, FieldDefinition>;
+
+/// Keys the filter syntax already uses, which custom fields would otherwise shadow.
+const RESERVED_NAMES: &[&str] = &["due", "tag", "project", "priority", "status"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FieldType {
+	Text,
+	Number,
+	Date,
+	/// One of the given options
+	Enum(Vec<String>),
+}
+
+/// A user-defined field, stored as events so that it is encrypted and synced with the tasks.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDefinition {
+	pub id: FieldId,
+	/// Used as the key when filtering, so it cannot contain spaces or colons
+	pub name: String,
+	pub field_type: FieldType,
+}
+
+/// Ordered by value, which is total since `parse_value` and `validate_task_fields` reject non-finite numbers.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(transparent)]
+pub struct Number(pub f64);
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Number {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FieldValue {
+	Text(String),
+	Number(Number),
+	Date(NaiveDate),
+	Enum(String),
+}
+
+impl FieldDefinition {
+	pub fn accepts(&self, value: &FieldValue) -> bool {
+		match (&self.field_type, value) {
+			(FieldType::Text, FieldValue::Text(_))
+			| (FieldType::Number, FieldValue::Number(_))
+			| (FieldType::Date, FieldValue::Date(_)) => true,
+			(FieldType::Enum(options), FieldValue::Enum(option)) => options.contains(option),
+			_ => false,
+		}
+	}
+
+	/// Reads a value typed into a filter query.
+	pub fn parse_value(&self, text: &str) -> Result<FieldValue, TasksError> {
+		let invalid =
+			|| TasksError::QueryError(format!("Invalid value for {}: {}", self.name, text));
+		match &self.field_type {
+			FieldType::Text => Ok(FieldValue::Text(text.to_string())),
+			// `f64` would otherwise accept NaN and infinity
+			FieldType::Number => text
+				.parse::<f64>()
+				.ok()
+				.filter(|number| number.is_finite())
+				.map(|number| FieldValue::Number(Number(number)))
+				.ok_or_else(invalid),
+			FieldType::Date => text.parse().map(FieldValue::Date).map_err(|_| invalid()),
+			FieldType::Enum(options) => options
+				.iter()
+				.find(|option| option.eq_ignore_ascii_case(text))
+				.map(|option| FieldValue::Enum(option.clone()))
+				.ok_or_else(invalid),
+		}
+	}
+}
+
+pub fn find_field_by_name<'a>(fields: &'a Fields, name: &str) -> Option<&'a FieldDefinition> {
+	fields
+		.values()
+		.find(|field| field.name.eq_ignore_ascii_case(name))
+}
+
+/// Existing values must still be valid under a changed definition.
+pub fn validate_field(
+	field: &FieldDefinition,
+	fields: &Fields,
+	tasks: &Tasks,
+) -> Result<(), TasksError> {
+	let invalid = |message: String| Err(TasksError::InvalidEventError(message));
+	if field.name.is_empty() || field.name.contains(|c: char| c.is_whitespace() || c == ':') {
+		return invalid(format!(
+			"Field name cannot be empty or contain spaces or colons: {}",
+			field.name
+		));
+	}
+	if RESERVED_NAMES
+		.iter()
+		.any(|name| name.eq_ignore_ascii_case(&field.name))
+	{
+		return invalid(format!("Field name is reserved: {}", field.name));
+	}
+	if let Some(other) =
+		find_field_by_name(fields, &field.name).filter(|other| other.id != field.id)
+	{
+		return invalid(format!("Field {} already exists", other.name));
+	}
+	if let FieldType::Enum(options) = &field.field_type {
+		if options.is_empty() {
+			return invalid(format!("Field {} needs at least one option", field.name));
+		}
+	}
+	let rejected = tasks.values().find(|task| {
+		task.fields
+			.get(&field.id)
+			.map_or(false, |value| !field.accepts(value))
+	});
+	match rejected {
+		Some(task) => invalid(format!(
+			"Task {} has a value which field {} would no longer accept",
+			task.id, field.name
+		)),
+		None => Ok(()),
+	}
+}
+
+/// Values for fields which have since been deleted are ignored, since other replicas may still hold them.
+pub fn validate_task_fields(task: &Task, fields: &Fields) -> Result<(), TasksError> {
+	for (field_id, value) in &task.fields {
+		// Also for deleted fields, whose values stay on the task and are still ordered
+		if let FieldValue::Number(Number(number)) = value {
+			if !number.is_finite() {
+				return Err(TasksError::InvalidEventError(format!(
+					"Non-finite number for field {} on task {}",
+					field_id, task.id
+				)));
+			}
+		}
+		if let Some(field) = fields.get(field_id) {
+			if !field.accepts(value) {
+				return Err(TasksError::InvalidEventError(format!(
+					"Invalid value for field {} on task {}",
+					field.name, task.id
+				)));
+			}
+		}
+	}
+	Ok(())
+}
+
+pub fn apply_field_event(fields: &mut Fields, event: &TaskEvent) {
+	match &event.data {
+		TaskEventData::SaveField(field) => {
+			fields.insert(field.id, field.clone());
+		}
+		TaskEventData::DeleteField(field_id) => {
+			fields.remove(field_id);
+		}
+		_ => {}
+	}
+}
+
+pub fn materialize_fields(events: &[TaskEvent]) -> Fields {
+	let mut fields = Fields::new();
+	for event in events {
+		apply_field_event(&mut fields, event);
+	}
+	fields
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn create_field(id: FieldId, name: &str, field_type: FieldType) -> FieldDefinition {
+		FieldDefinition {
+			id,
+			name: name.to_string(),
+			field_type,
+		}
+	}
+
+	fn create_size_field(options: &[&str]) -> FieldDefinition {
+		create_field(
+			1,
+			"size",
+			FieldType::Enum(options.iter().map(|option| option.to_string()).collect()),
+		)
+	}
+
+	#[test]
+	fn test_parse_value() {
+		let estimate = create_field(2, "estimate", FieldType::Number);
+		let size = create_size_field(&["Small", "Large"]);
+
+		assert_eq!(
+			estimate.parse_value("2.5").unwrap(),
+			FieldValue::Number(Number(2.5))
+		);
+		assert!(estimate.parse_value("soon").is_err());
+		assert!(estimate.parse_value("NaN").is_err());
+		assert!(estimate.parse_value("-inf").is_err());
+		assert_eq!(
+			size.parse_value("large").unwrap(),
+			FieldValue::Enum("Large".to_string())
+		);
+		assert!(size.parse_value("medium").is_err());
+	}
+
+	#[test]
+	fn test_validate_field() {
+		let size = create_size_field(&["Small", "Large"]);
+		let fields = Fields::from([(1, size.clone())]);
+		let tasks = Tasks::from([(
+			1,
+			Task {
+				id: 1,
+				fields: BTreeMap::from([(1, FieldValue::Enum("Large".to_string()))]),
+				..Default::default()
+			},
+		)]);
+
+		assert!(validate_field(&size, &fields, &tasks).is_ok());
+		assert!(validate_field(&create_size_field(&["Small"]), &fields, &tasks).is_err());
+		assert!(validate_field(&create_size_field(&[]), &fields, &Tasks::new()).is_err());
+		for name in ["", "t-shirt size", "size:", "Due"] {
+			let field = create_field(2, name, FieldType::Text);
+			assert!(
+				validate_field(&field, &Fields::new(), &tasks).is_err(),
+				"{}",
+				name
+			);
+		}
+		let duplicate = create_field(2, "Size", FieldType::Text);
+		assert!(validate_field(&duplicate, &fields, &tasks).is_err());
+	}
+
+	#[test]
+	fn test_validate_task_fields() {
+		let fields = Fields::from([(1, create_size_field(&["Small"]))]);
+		let with_value = |field_id: FieldId, value: FieldValue| Task {
+			fields: BTreeMap::from([(field_id, value)]),
+			..Default::default()
+		};
+
+		assert!(validate_task_fields(
+			&with_value(1, FieldValue::Enum("Small".to_string())),
+			&fields
+		)
+		.is_ok());
+		assert!(validate_task_fields(
+			&with_value(1, FieldValue::Enum("Huge".to_string())),
+			&fields
+		)
+		.is_err());
+		assert!(validate_task_fields(
+			&with_value(1, FieldValue::Text("Small".to_string())),
+			&fields
+		)
+		.is_err());
+		assert!(validate_task_fields(
+			&with_value(9, FieldValue::Text("Deleted".to_string())),
+			&fields
+		)
+		.is_ok());
+		let fields = Fields::from([(2, create_field(2, "estimate", FieldType::Number))]);
+		for number in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+			for field_id in [2, 9] {
+				assert!(validate_task_fields(
+					&with_value(field_id, FieldValue::Number(Number(number))),
+					&fields
+				)
+				.is_err());
+			}
+		}
+		assert!(
+			validate_task_fields(&with_value(2, FieldValue::Number(Number(2.5))), &fields).is_ok()
+		);
+	}
+}
@@ -0,0 +1,407 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, NaiveDate, TimeZone};
+
+use crate::dependency::is_blocked;
+use crate::due::{get_due_date, is_overdue};
+use crate::error::TasksError;
+use crate::field::{find_field_by_name, FieldId, FieldValue, Fields};
+use crate::reducer::Tasks;
+use crate::task::{Priority, Task};
+use crate::workflow::Status;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+	Lt,
+	Le,
+	Eq,
+	Ge,
+	Gt,
+}
+
+impl Comparison {
+	fn matches(self, ordering: Ordering) -> bool {
+		match self {
+			Comparison::Lt => ordering == Ordering::Less,
+			Comparison::Le => ordering != Ordering::Greater,
+			Comparison::Eq => ordering == Ordering::Equal,
+			Comparison::Ge => ordering != Ordering::Less,
+			Comparison::Gt => ordering == Ordering::Greater,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+	And(Vec<Filter>),
+	Or(Vec<Filter>),
+	Not(Box<Filter>),
+	Due(Comparison, NaiveDate),
+	Undated,
+	Tag(String),
+	Project(String),
+	Priority(Comparison, Priority),
+	Status(Status),
+	/// Done, but not cancelled
+	Completed,
+	/// Done or cancelled
+	Closed,
+	Blocked,
+	Overdue,
+	/// Case-insensitive substring of the description
+	Text(String),
+	/// Tasks without a value for the field never match
+	Field(FieldId, Comparison, FieldValue),
+}
+
+fn query_error(message: String) -> TasksError {
+	TasksError::QueryError(message)
+}
+
+fn parse_comparison(value: &str) -> (Comparison, &str) {
+	for (operator, comparison) in [
+		("<=", Comparison::Le),
+		(">=", Comparison::Ge),
+		("<", Comparison::Lt),
+		(">", Comparison::Gt),
+		("=", Comparison::Eq),
+	] {
+		if let Some(rest) = value.strip_prefix(operator) {
+			return (comparison, rest);
+		}
+	}
+	(Comparison::Eq, value)
+}
+
+fn parse_priority(value: &str) -> Result<Priority, TasksError> {
+	match value.to_lowercase().as_str() {
+		"low" => Ok(Priority::Low),
+		"normal" => Ok(Priority::Normal),
+		"high" => Ok(Priority::High),
+		"urgent" => Ok(Priority::Urgent),
+		_ => Err(query_error(format!("Unknown priority: {}", value))),
+	}
+}
+
+fn parse_status(value: &str) -> Result<Status, TasksError> {
+	match value.to_lowercase().replace(['-', '_'], "").as_str() {
+		"todo" => Ok(Status::Todo),
+		"inprogress" => Ok(Status::InProgress),
+		"waiting" => Ok(Status::Waiting),
+		"done" => Ok(Status::Done),
+		"cancelled" => Ok(Status::Cancelled),
+		_ => Err(query_error(format!("Unknown status: {}", value))),
+	}
+}
+
+fn parse_term(term: &str, fields: &Fields) -> Result<Filter, TasksError> {
+	if let Some(negated) = term.strip_prefix('-').filter(|rest| !rest.is_empty()) {
+		return Ok(Filter::Not(Box::new(parse_term(negated, fields)?)));
+	}
+
+	let (key, value) = match term.split_once(':') {
+		Some((key, value)) => (key, value),
+		None => {
+			return Ok(match term.to_lowercase().as_str() {
+				"completed" => Filter::Completed,
+				"closed" => Filter::Closed,
+				"blocked" => Filter::Blocked,
+				"overdue" => Filter::Overdue,
+				_ => Filter::Text(term.to_lowercase()),
+			})
+		}
+	};
+	if value.is_empty() {
+		return Err(query_error(format!("Missing value for {}", key)));
+	}
+
+	match key {
+		"due" if value == "none" => Ok(Filter::Undated),
+		"due" => {
+			let (comparison, date) = parse_comparison(value);
+			let date = date
+				.parse()
+				.map_err(|_| query_error(format!("Invalid date: {}", date)))?;
+			Ok(Filter::Due(comparison, date))
+		}
+		"tag" => Ok(Filter::Tag(value.to_string())),
+		"project" => Ok(Filter::Project(value.to_string())),
+		"priority" => {
+			let (comparison, priority) = parse_comparison(value);
+			Ok(Filter::Priority(comparison, parse_priority(priority)?))
+		}
+		"status" => Ok(Filter::Status(parse_status(value)?)),
+		_ => match find_field_by_name(fields, key) {
+			Some(field) => {
+				let (comparison, value) = parse_comparison(value);
+				Ok(Filter::Field(
+					field.id,
+					comparison,
+					field.parse_value(value)?,
+				))
+			}
+			None => Err(query_error(format!("Unknown filter: {}", key))),
+		},
+	}
+}
+
+/// Parses space-separated terms which must all match, with `OR` between alternatives, eg
+/// `due:<2026-11-01 tag:work -closed OR priority:>=high`.
+///
+/// Terms are `due:[<|<=|=|>=|>]YYYY-MM-DD`, `due:none`, `tag:`, `project:`, `priority:[<|<=|=|>=|>]level`,
+/// `status:`, `completed` (done), `closed` (done or cancelled), `blocked`, `overdue` or any other word
+/// to find in the description. A custom field's name works as a key too, with the same comparisons,
+/// eg `estimate:>2`. A leading `-` negates a term. An empty query matches everything,
+/// but each side of an `OR` needs at least one term.
+pub fn parse_filter(query: &str, fields: &Fields) -> Result<Filter, TasksError> {
+	let mut alternatives = Vec::new();
+	let mut terms = Vec::new();
+	let missing_alternative = || query_error("Expected a term on each side of OR".to_string());
+	for word in query.split_whitespace() {
+		if word == "OR" {
+			if terms.is_empty() {
+				return Err(missing_alternative());
+			}
+			alternatives.push(Filter::And(std::mem::take(&mut terms)));
+		} else {
+			terms.push(parse_term(word, fields)?);
+		}
+	}
+	if terms.is_empty() && !alternatives.is_empty() {
+		return Err(missing_alternative());
+	}
+	alternatives.push(Filter::And(terms));
+
+	Ok(if alternatives.len() == 1 {
+		alternatives.remove(0)
+	} else {
+		Filter::Or(alternatives)
+	})
+}
+
+/// Text is compared case-insensitively.
+pub fn compare_field_values(a: &FieldValue, b: &FieldValue) -> Ordering {
+	match (a, b) {
+		(FieldValue::Text(a), FieldValue::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+		(a, b) => a.cmp(b),
+	}
+}
+
+pub fn matches_filter<Z: TimeZone>(
+	filter: &Filter,
+	task: &Task,
+	tasks: &Tasks,
+	now: &DateTime<Z>,
+) -> bool {
+	match filter {
+		Filter::And(filters) => filters
+			.iter()
+			.all(|filter| matches_filter(filter, task, tasks, now)),
+		Filter::Or(filters) => filters
+			.iter()
+			.any(|filter| matches_filter(filter, task, tasks, now)),
+		Filter::Not(filter) => !matches_filter(filter, task, tasks, now),
+		Filter::Due(comparison, date) => task.deadline.as_ref().map_or(false, |deadline| {
+			comparison.matches(get_due_date(deadline, now).cmp(date))
+		}),
+		Filter::Undated => task.deadline.is_none(),
+		Filter::Tag(tag) => task.tags.contains(tag),
+		Filter::Project(project) => task.project.as_ref() == Some(project),
+		Filter::Priority(comparison, priority) => comparison.matches(task.priority.cmp(priority)),
+		Filter::Status(status) => task.status == *status,
+		Filter::Completed => task.status == Status::Done,
+		Filter::Closed => task.status.is_closed(),
+		Filter::Blocked => is_blocked(tasks, task),
+		Filter::Overdue => is_overdue(task, now),
+		Filter::Text(text) => task.description.to_lowercase().contains(text),
+		Filter::Field(field_id, comparison, value) => {
+			task.fields.get(field_id).map_or(false, |task_value| {
+				comparison.matches(compare_field_values(task_value, value))
+			})
+		}
+	}
+}
+
+pub fn filter_tasks<Z: TimeZone>(filter: &Filter, tasks: &Tasks, now: &DateTime<Z>) -> Vec<Task> {
+	tasks
+		.values()
+		.filter(|task| matches_filter(filter, task, tasks, now))
+		.cloned()
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::field::{FieldDefinition, FieldType, Number};
+	use crate::task::{Deadline, TaskId};
+	use chrono::Utc;
+	use std::collections::BTreeMap;
+
+	fn get_now() -> DateTime<Utc> {
+		"2026-10-21T12:00:00Z".parse().unwrap()
+	}
+
+	fn create_tasks() -> Tasks {
+		let create_task = |id: TaskId, deadline: Option<&str>, tag: &str, status: Status| Task {
+			id,
+			description: format!("Task {}", id),
+			deadline: deadline.map(|date| Deadline::Date(date.parse().unwrap())),
+			tags: vec![tag.to_string()],
+			status,
+			..Default::default()
+		};
+		Tasks::from([
+			(
+				1,
+				with_field(
+					create_task(1, Some("2026-10-20"), "work", Status::Todo),
+					1,
+					FieldValue::Number(Number(2.5)),
+				),
+			),
+			(2, create_task(2, Some("2026-10-25"), "work", Status::Done)),
+			(
+				3,
+				with_field(
+					create_task(3, Some("2026-11-05"), "work", Status::InProgress),
+					1,
+					FieldValue::Number(Number(8.0)),
+				),
+			),
+			(
+				4,
+				Task {
+					project: Some("infra".to_string()),
+					priority: Priority::Urgent,
+					..with_field(
+						create_task(4, None, "home", Status::Todo),
+						2,
+						FieldValue::Text("Acme".to_string()),
+					)
+				},
+			),
+		])
+	}
+
+	fn get_fields() -> Fields {
+		let create_field = |id: FieldId, name: &str, field_type: FieldType| {
+			(
+				id,
+				FieldDefinition {
+					id,
+					name: name.to_string(),
+					field_type,
+				},
+			)
+		};
+		Fields::from([
+			create_field(1, "estimate", FieldType::Number),
+			create_field(2, "customer", FieldType::Text),
+		])
+	}
+
+	fn with_field(task: Task, field_id: FieldId, value: FieldValue) -> Task {
+		Task {
+			fields: BTreeMap::from([(field_id, value)]),
+			..task
+		}
+	}
+
+	fn query(query: &str) -> Vec<TaskId> {
+		let filter = parse_filter(query, &get_fields()).unwrap();
+		filter_tasks(&filter, &create_tasks(), &get_now())
+			.iter()
+			.map(|task| task.id)
+			.collect()
+	}
+
+	#[test]
+	fn test_parse_filter() {
+		assert_eq!(
+			parse_filter(
+				"due:<2026-11-01 -completed OR priority:>=high",
+				&Fields::new()
+			)
+			.unwrap(),
+			Filter::Or(vec![
+				Filter::And(vec![
+					Filter::Due(Comparison::Lt, "2026-11-01".parse().unwrap()),
+					Filter::Not(Box::new(Filter::Completed)),
+				]),
+				Filter::And(vec![Filter::Priority(Comparison::Ge, Priority::High)]),
+			])
+		);
+		assert_eq!(
+			parse_filter("", &Fields::new()).unwrap(),
+			Filter::And(Vec::new())
+		);
+	}
+
+	#[test]
+	fn test_parse_filter_errors() {
+		for invalid in [
+			"due:tomorrow",
+			"priority:extreme",
+			"colour:red",
+			"tag:",
+			"tag:work OR",
+			"OR tag:work",
+			"tag:work OR OR tag:home",
+		] {
+			assert!(
+				matches!(
+					parse_filter(invalid, &Fields::new()),
+					Err(TasksError::QueryError(_))
+				),
+				"{}",
+				invalid
+			);
+		}
+	}
+
+	#[test]
+	fn test_filter_tasks() {
+		assert_eq!(query("due:<2026-11-01 tag:work -completed"), vec![1]);
+		assert_eq!(query("due:>=2026-10-25"), vec![2, 3]);
+		assert_eq!(query("due:none project:infra"), vec![4]);
+		assert_eq!(query("overdue OR priority:urgent"), vec![1, 4]);
+		assert_eq!(query("status:in-progress"), vec![3]);
+		assert_eq!(query("task -3"), vec![1, 2, 4]);
+		assert_eq!(query(""), vec![1, 2, 3, 4]);
+	}
+
+	#[test]
+	fn test_completed_excludes_cancelled_tasks() {
+		let cancelled = Task {
+			status: Status::Cancelled,
+			..Default::default()
+		};
+		let tasks = Tasks::from([(0, cancelled.clone())]);
+
+		assert!(!matches_filter(
+			&Filter::Completed,
+			&cancelled,
+			&tasks,
+			&get_now()
+		));
+		assert!(matches_filter(
+			&Filter::Closed,
+			&cancelled,
+			&tasks,
+			&get_now()
+		));
+	}
+
+	#[test]
+	fn test_filter_tasks_by_custom_fields() {
+		assert_eq!(query("estimate:>=2.5"), vec![1, 3]);
+		assert_eq!(query("Estimate:<3"), vec![1]);
+		assert_eq!(query("customer:acme"), vec![4]);
+		assert_eq!(query("-customer:acme"), vec![1, 2, 3]);
+		assert!(matches!(
+			parse_filter("estimate:soon", &get_fields()),
+			Err(TasksError::QueryError(_))
+		));
+	}
+}
@@ -0,0 +1,315 @@
+use std::fmt;
+use std::fs::{self, create_dir_all, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use filetime::{set_file_mtime, FileTime};
+use serde::{Deserialize, Serialize};
+
+use crate::error::TasksError;
+use crate::fs::read_file_into_buffer;
+
+/// How long to keep retrying while another process holds the lock
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+pub const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+/// Writes take milliseconds, so a lock this old was left behind by a crashed process
+pub const STALE_LOCK_AGE: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockOwner {
+	pub pid: u32,
+	pub host: String,
+}
+
+impl LockOwner {
+	fn current() -> Self {
+		LockOwner {
+			pid: process::id(),
+			host: get_hostname(),
+		}
+	}
+}
+
+impl fmt::Display for LockOwner {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "process {} on {}", self.pid, self.host)
+	}
+}
+
+fn get_hostname() -> String {
+	["HOSTNAME", "COMPUTERNAME"]
+		.iter()
+		.find_map(|name| std::env::var(name).ok())
+		.or_else(|| {
+			Command::new("hostname")
+				.output()
+				.ok()
+				.and_then(|output| String::from_utf8(output.stdout).ok())
+		})
+		.map(|host| host.trim().to_string())
+		.filter(|host| !host.is_empty())
+		.unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Only a process which does not exist is dead; another user's process can't be signalled but is still alive.
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+	// Zero and negative pids would signal whole process groups
+	let pid = match libc::pid_t::try_from(pid) {
+		Ok(pid) if pid > 0 => pid,
+		_ => return false,
+	};
+	if unsafe { libc::kill(pid, 0) } == 0 {
+		return true;
+	}
+	io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+#[cfg(not(unix))]
+fn is_process_alive(_pid: u32) -> bool {
+	true
+}
+
+/// An advisory lock held for as long as the value lives; the lock file is removed on drop.
+/// It is not reentrant: acquiring it again while holding it times out, so functions called with it held
+/// take the lock as an argument or document that the caller must hold it.
+#[derive(Debug)]
+pub struct VaultLock {
+	path: PathBuf,
+}
+
+impl VaultLock {
+	pub fn acquire(path: &Path) -> Result<Self, TasksError> {
+		let started = Instant::now();
+		loop {
+			match Self::try_acquire(path)? {
+				Ok(lock) => return Ok(lock),
+				Err(owner) if started.elapsed() >= LOCK_TIMEOUT => {
+					return Err(TasksError::VaultLockedError(
+						owner
+							.map(|owner| owner.to_string())
+							.unwrap_or_else(|| "an unknown process".to_string()),
+					));
+				}
+				Err(_) => thread::sleep(LOCK_RETRY_INTERVAL),
+			}
+		}
+	}
+
+	/// Returns the current owner, if known, when the lock is held by another live process,
+	/// or none after taking away a stale lock, so that `acquire` tries again.
+	fn try_acquire(path: &Path) -> Result<Result<Self, Option<LockOwner>>, TasksError> {
+		if let Some(parent_dir) = path.parent() {
+			create_dir_all(parent_dir)?;
+		}
+
+		match OpenOptions::new().write(true).create_new(true).open(path) {
+			Ok(mut file) => {
+				let lock = VaultLock {
+					path: path.to_path_buf(),
+				};
+				file.write_all(&serde_json::to_vec(&LockOwner::current())?)?;
+				Ok(Ok(lock))
+			}
+			Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+				let owner = read_lock_owner(path);
+				if !is_stale(path, owner.as_ref()) {
+					return Ok(Err(owner));
+				}
+				remove_stale_lock(path)?;
+				Ok(Err(None))
+			}
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	/// Keeps the lock from looking stale during operations which take longer than `STALE_LOCK_AGE`.
+	/// Fails if another process has taken the lock away in the meantime.
+	pub fn refresh(&self) -> Result<(), TasksError> {
+		if !self.is_owned() {
+			return Err(TasksError::VaultLockedError(
+				"the lock was taken over during a long operation".to_string(),
+			));
+		}
+		set_file_mtime(&self.path, FileTime::now())?;
+		Ok(())
+	}
+
+	fn is_owned(&self) -> bool {
+		read_lock_owner(&self.path) == Some(LockOwner::current())
+	}
+}
+
+/// Moves the lock aside before deleting it, since only one process can move a given file. If another
+/// process replaced the stale lock with its own in the meantime, that one is put back instead.
+fn remove_stale_lock(path: &Path) -> Result<(), TasksError> {
+	let mut moved_name = path.as_os_str().to_os_string();
+	moved_name.push(format!(".stale-{}", process::id()));
+	let moved_path = PathBuf::from(moved_name);
+	match fs::rename(path, &moved_path) {
+		Ok(()) => {}
+		// Another process took it away first
+		Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+		Err(error) => return Err(error.into()),
+	}
+
+	if !is_stale(&moved_path, read_lock_owner(&moved_path).as_ref()) {
+		// Fails rather than overwriting if yet another process has locked since
+		let _ = fs::hard_link(&moved_path, path);
+	}
+	fs::remove_file(&moved_path)?;
+	Ok(())
+}
+
+/// Leaves the file alone if it was taken away as stale, since it then belongs to another process.
+impl Drop for VaultLock {
+	fn drop(&mut self) {
+		if self.is_owned() {
+			let _ = fs::remove_file(&self.path);
+		}
+	}
+}
+
+fn read_lock_owner(path: &Path) -> Option<LockOwner> {
+	read_file_into_buffer(&path.to_path_buf())
+		.ok()
+		.and_then(|buffer| serde_json::from_slice(&buffer).ok())
+}
+
+fn get_lock_age(path: &Path) -> Duration {
+	path.metadata()
+		.and_then(|metadata| metadata.modified())
+		.ok()
+		.and_then(|modified| SystemTime::now().duration_since(modified).ok())
+		.unwrap_or_default()
+}
+
+fn is_stale(path: &Path, owner: Option<&LockOwner>) -> bool {
+	let age = get_lock_age(path);
+	if age > STALE_LOCK_AGE {
+		return true;
+	}
+
+	match owner {
+		Some(owner) => {
+			let current = LockOwner::current();
+			owner.host == current.host && owner.pid != current.pid && !is_process_alive(owner.pid)
+		}
+		// The owner may not have finished writing its details yet
+		None => age > LOCK_TIMEOUT,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::fs::write_buffer_to_file;
+	use tempfile::tempdir;
+
+	#[test]
+	fn test_acquire_and_release() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("lock");
+
+		let lock = VaultLock::acquire(&path).unwrap();
+		assert_eq!(read_lock_owner(&path), Some(LockOwner::current()));
+
+		drop(lock);
+		assert!(!path.exists());
+	}
+
+	#[test]
+	fn test_acquire_held_lock() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("lock");
+		let owner = LockOwner {
+			pid: process::id(),
+			host: "another-host".to_string(),
+		};
+		write_buffer_to_file(&path, &serde_json::to_vec(&owner).unwrap()).unwrap();
+
+		assert_eq!(
+			VaultLock::try_acquire(&path).unwrap().unwrap_err(),
+			Some(owner)
+		);
+	}
+
+	fn set_lock_age(path: &Path, age: Duration) {
+		set_file_mtime(path, FileTime::from_system_time(SystemTime::now() - age)).unwrap();
+	}
+
+	#[test]
+	fn test_acquire_stale_lock() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("lock");
+		let owner = LockOwner {
+			pid: process::id(),
+			host: "another-host".to_string(),
+		};
+		write_buffer_to_file(&path, &serde_json::to_vec(&owner).unwrap()).unwrap();
+		set_lock_age(&path, STALE_LOCK_AGE * 2);
+
+		let lock = VaultLock::acquire(&path).unwrap();
+		assert_eq!(read_lock_owner(&path), Some(LockOwner::current()));
+		drop(lock);
+	}
+
+	#[test]
+	fn test_acquire_unreadable_lock() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("lock");
+		write_buffer_to_file(&path, b"garbage").unwrap();
+		assert_eq!(VaultLock::try_acquire(&path).unwrap().unwrap_err(), None);
+
+		set_lock_age(&path, LOCK_TIMEOUT * 2);
+		assert_eq!(VaultLock::try_acquire(&path).unwrap().unwrap_err(), None);
+		assert!(!path.exists());
+		assert!(VaultLock::try_acquire(&path).unwrap().is_ok());
+	}
+
+	#[test]
+	fn test_remove_stale_lock_keeps_a_live_replacement() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("lock");
+		let owner = LockOwner {
+			pid: process::id(),
+			host: "another-host".to_string(),
+		};
+		write_buffer_to_file(&path, &serde_json::to_vec(&owner).unwrap()).unwrap();
+
+		remove_stale_lock(&path).unwrap();
+
+		assert_eq!(read_lock_owner(&path), Some(owner));
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn test_drop_keeps_a_lock_taken_over() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("lock");
+		let lock = VaultLock::acquire(&path).unwrap();
+		set_lock_age(&path, STALE_LOCK_AGE * 2);
+		lock.refresh().unwrap();
+		assert!(get_lock_age(&path) < STALE_LOCK_AGE);
+
+		let owner = LockOwner {
+			pid: process::id(),
+			host: "another-host".to_string(),
+		};
+		write_buffer_to_file(&path, &serde_json::to_vec(&owner).unwrap()).unwrap();
+		assert!(lock.refresh().is_err());
+
+		drop(lock);
+		assert_eq!(read_lock_owner(&path), Some(owner));
+	}
+
+	#[test]
+	fn test_is_process_alive() {
+		assert!(is_process_alive(process::id()));
+		assert!(!is_process_alive(0));
+		assert!(!is_process_alive(u32::MAX));
+	}
+}
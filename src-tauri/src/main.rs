@@ -1,36 +1,130 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod archive;
+mod attachment;
+mod bulk;
 mod command;
 mod config;
+mod context;
 mod crypto;
+mod dependency;
+mod due;
 mod error;
 mod event;
+mod field;
+mod filter;
 mod fs;
+mod lockfile;
+mod markdown;
+mod migration;
+mod organize;
+mod recurrence;
+mod reducer;
+mod reminder;
+mod replica;
+mod search;
 mod storage;
+mod subtask;
 mod task;
+mod timetracking;
+mod trash;
 mod util;
+mod vault;
+mod view;
+mod watcher;
+mod workflow;
 
 use crate::command::{
-	change_password, check_exists, load_events, lock, save_event, unlock, update_config,
+	add_attachment, add_time_entry, archive_completed_tasks, blocked_tasks, change_password,
+	check_exists, collect_attachment_garbage, create_vault, cycle_times, delete_field,
+	delete_vault, delete_view, due_groups, find_vaults, list_archived_tasks, list_fields,
+	list_links, list_subtasks, list_tags, list_tasks_by_priority, list_tasks_by_project,
+	list_tasks_by_tag, list_trash, list_vaults, list_views, load_events, lock,
+	next_actionable_tasks, open_vault, postpone_overdue, query_tasks, read_attachment, rename_tag,
+	rename_vault, render_details, replica_status, restore_archived_task, restore_deleted_task,
+	resync_replica, running_timer, save_event, save_events_batch, save_field, save_view,
+	search_archived_tasks, search_tasks, set_status_bulk, start_timer, stop_timer, switch_vault,
+	task_progress, time_report, unlock, update_config, view_tasks,
 };
 use crate::config::AppConfig;
+use crate::context::AppContext;
 use crate::crypto::EncryptionKey;
 use crate::event::EventStore;
+use crate::reminder::{watch_reminders, SystemClock};
+use crate::search::SearchStore;
+use crate::watcher::watch_replicas;
 
 fn main() {
+	let app_context =
+		AppContext::from_environment().expect("error while resolving the data directory");
 	tauri::Builder::default()
 		.manage(EncryptionKey(Default::default()))
-		.manage(AppConfig::new())
+		.manage(AppConfig::new(&app_context))
+		.manage(app_context)
 		.manage(EventStore::new())
+		.manage(SearchStore::new())
+		.setup(|app| {
+			watch_replicas(app.handle());
+			watch_reminders(app.handle(), SystemClock);
+			Ok(())
+		})
 		.invoke_handler(tauri::generate_handler![
+			add_attachment,
+			add_time_entry,
+			archive_completed_tasks,
+			blocked_tasks,
 			change_password,
 			check_exists,
+			collect_attachment_garbage,
+			create_vault,
+			cycle_times,
+			delete_field,
+			delete_vault,
+			delete_view,
+			due_groups,
+			find_vaults,
+			list_archived_tasks,
+			list_fields,
+			list_links,
+			list_subtasks,
+			list_tags,
+			list_tasks_by_priority,
+			list_tasks_by_project,
+			list_tasks_by_tag,
+			list_trash,
+			list_vaults,
+			list_views,
 			load_events,
 			lock,
+			next_actionable_tasks,
+			open_vault,
+			postpone_overdue,
+			query_tasks,
+			read_attachment,
+			rename_tag,
+			rename_vault,
+			render_details,
+			replica_status,
+			resync_replica,
+			restore_archived_task,
+			restore_deleted_task,
+			running_timer,
 			save_event,
+			save_events_batch,
+			save_field,
+			save_view,
+			search_archived_tasks,
+			search_tasks,
+			set_status_bulk,
+			start_timer,
+			stop_timer,
+			switch_vault,
+			task_progress,
+			time_report,
 			unlock,
 			update_config,
+			view_tasks,
 		])
 		.run(tauri::generate_context!())
 		.expect("error while running tauri application");
@@ -0,0 +1,283 @@
+use ammonia::Builder;
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+use serde::Serialize;
+
+use crate::reducer::Tasks;
+use crate::subtask::ChecklistItem;
+use crate::task::TaskId;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+	pub url: String,
+	pub text: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskLink {
+	pub task_id: TaskId,
+	pub url: String,
+	pub text: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RenderedDetails {
+	pub html: String,
+	pub checklist: Vec<ChecklistItem>,
+	pub links: Vec<Link>,
+}
+
+fn parse(markdown: &str) -> Parser<'_, '_> {
+	Parser::new_ext(
+		markdown,
+		Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES,
+	)
+}
+
+/// Raw HTML in the markdown is sanitized away here, so the webview never sees it.
+/// Checklist boxes survive sanitization but can only ever be disabled checkboxes.
+/// Images are dropped, since loading a remote one would tell its server the task was viewed.
+pub fn render_html(markdown: &str) -> String {
+	let mut unsafe_html = String::new();
+	html::push_html(&mut unsafe_html, parse(markdown));
+	Builder::default()
+		.rm_tags(&["img"])
+		.add_tags(&["input"])
+		.add_tag_attributes("input", &["checked"])
+		.set_tag_attribute_value("input", "type", "checkbox")
+		.set_tag_attribute_value("input", "disabled", "")
+		.clean(&unsafe_html)
+		.to_string()
+}
+
+/// Text of the task list items, leaving out any nested list.
+pub fn extract_checklist(markdown: &str) -> Vec<ChecklistItem> {
+	let mut checklist = Vec::new();
+	let mut current: Option<ChecklistItem> = None;
+	for event in parse(markdown) {
+		match event {
+			Event::TaskListMarker(done) => {
+				current = Some(ChecklistItem {
+					text: String::new(),
+					done,
+				})
+			}
+			Event::Text(text) | Event::Code(text) => {
+				if let Some(item) = current.as_mut() {
+					item.text.push_str(&text);
+				}
+			}
+			Event::SoftBreak | Event::HardBreak => {
+				if let Some(item) = current.as_mut() {
+					item.text.push(' ');
+				}
+			}
+			Event::Start(Tag::List(_)) | Event::End(Tag::Item) => {
+				if let Some(mut item) = current.take() {
+					item.text = item.text.trim().to_string();
+					checklist.push(item);
+				}
+			}
+			_ => {}
+		}
+	}
+	checklist
+}
+
+/// Lowercased, ignoring the whitespace browsers ignore, eg `java\tscript:`.
+fn get_url_scheme(url: &str) -> Option<String> {
+	let url: String = url
+		.trim_start_matches(|c: char| c <= ' ')
+		.chars()
+		.filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+		.collect();
+	let (scheme, _) = url.split_once(':')?;
+	let mut chars = scheme.chars();
+	let is_scheme = chars.next()?.is_ascii_alphabetic()
+		&& chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+	is_scheme.then(|| scheme.to_ascii_lowercase())
+}
+
+/// Only the schemes `render_html` lets through, so `javascript:` links can't reach the UI this way either.
+/// Relative links are left out, since there is nothing for them to be relative to.
+fn is_allowed_url(url: &str) -> bool {
+	get_url_scheme(url).map_or(false, |scheme| {
+		Builder::default()
+			.clone_url_schemes()
+			.contains(scheme.as_str())
+	})
+}
+
+/// Inline, reference and autolinks with an allowed scheme, in order of appearance.
+pub fn extract_links(markdown: &str) -> Vec<Link> {
+	let mut links = Vec::new();
+	let mut current: Option<Link> = None;
+	for event in parse(markdown) {
+		match event {
+			Event::Start(Tag::Link(_, url, _)) if is_allowed_url(&url) => {
+				current = Some(Link {
+					url: url.to_string(),
+					text: String::new(),
+				})
+			}
+			Event::Text(text) | Event::Code(text) => {
+				if let Some(link) = current.as_mut() {
+					link.text.push_str(&text);
+				}
+			}
+			Event::End(Tag::Link(..)) => links.extend(current.take()),
+			_ => {}
+		}
+	}
+	links
+}
+
+pub fn render_details(details: &str) -> RenderedDetails {
+	RenderedDetails {
+		html: render_html(details),
+		checklist: extract_checklist(details),
+		links: extract_links(details),
+	}
+}
+
+pub fn get_all_links(tasks: &Tasks) -> Vec<TaskLink> {
+	tasks
+		.values()
+		.flat_map(|task| {
+			extract_links(&task.details)
+				.into_iter()
+				.map(move |link| TaskLink {
+					task_id: task.id,
+					url: link.url,
+					text: link.text,
+				})
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::task::Task;
+
+	#[test]
+	fn test_render_html_sanitizes() {
+		let html = render_html(
+			"**Bold** <script>alert(1)</script>\n\n[Click](javascript:alert(1)) <img src=x onerror=alert(1)>",
+		);
+
+		assert!(html.contains("<strong>Bold</strong>"));
+		assert!(!html.contains("script"));
+		assert!(!html.contains("javascript"));
+		assert!(!html.contains("onerror"));
+	}
+
+	#[test]
+	fn test_render_html_drops_images() {
+		let html = render_html(
+			"![pixel](https://example.com/pixel.gif) <img src=\"https://example.com/a.gif\">",
+		);
+
+		assert!(!html.contains("img"));
+		assert!(!html.contains("example.com"));
+	}
+
+	#[test]
+	fn test_extract_links_filters_schemes() {
+		let links = extract_links(
+			"[a](javascript:alert(1)) [b](<JavaScript:alert(1)>) [c](data:text/html,hi) \
+			 [d](notes.txt) [e](mailto:me@example.com) [f](HTTPS://example.com)",
+		);
+
+		assert_eq!(
+			links
+				.iter()
+				.map(|link| link.text.as_str())
+				.collect::<Vec<_>>(),
+			vec!["e", "f"]
+		);
+		assert_eq!(
+			get_url_scheme(" java\tscript:alert(1)"),
+			Some("javascript".to_string())
+		);
+		assert_eq!(get_url_scheme("notes.txt"), None);
+	}
+
+	#[test]
+	fn test_render_html_keeps_checkboxes_disabled() {
+		let html = render_html("- [x] Done\n- [ ] Todo");
+
+		assert!(html.contains("checked"));
+		assert_eq!(html.matches("disabled").count(), 2);
+		assert_eq!(html.matches("type=\"checkbox\"").count(), 2);
+	}
+
+	#[test]
+	fn test_extract_checklist() {
+		let checklist = extract_checklist(
+			"Intro\n\n- [x] Book `flights`\n- [ ] Pack\n  - [ ] Socks\n- Not a checklist item",
+		);
+
+		assert_eq!(
+			checklist,
+			vec![
+				ChecklistItem {
+					text: "Book flights".to_string(),
+					done: true,
+				},
+				ChecklistItem {
+					text: "Pack".to_string(),
+					done: false,
+				},
+				ChecklistItem {
+					text: "Socks".to_string(),
+					done: false,
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_get_all_links() {
+		let tasks = Tasks::from([
+			(
+				1,
+				Task {
+					id: 1,
+					details: "See [the *docs*](https://example.com/docs) and <https://example.org>"
+						.to_string(),
+					..Default::default()
+				},
+			),
+			(
+				2,
+				Task {
+					id: 2,
+					details: "[Spec][spec]\n\n[spec]: https://example.com/spec".to_string(),
+					..Default::default()
+				},
+			),
+		]);
+
+		assert_eq!(
+			get_all_links(&tasks),
+			vec![
+				TaskLink {
+					task_id: 1,
+					url: "https://example.com/docs".to_string(),
+					text: "the docs".to_string(),
+				},
+				TaskLink {
+					task_id: 1,
+					url: "https://example.org".to_string(),
+					text: "https://example.org".to_string(),
+				},
+				TaskLink {
+					task_id: 2,
+					url: "https://example.com/spec".to_string(),
+					text: "Spec".to_string(),
+				},
+			]
+		);
+	}
+}
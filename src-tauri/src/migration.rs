@@ -0,0 +1,392 @@
+use serde_json::Value;
+
+use crate::config::SERIALIZATION_VERSION;
+use crate::error::TasksError;
+use crate::storage::TasksData;
+
+/// Upgrades serialized tasks data in place by exactly one version.
+pub type Migration = fn(&mut Value) -> Result<(), TasksError>;
+
+/// The migration at index `n` upgrades version `n + 1` to version `n + 2`.
+pub const MIGRATIONS: &[Migration] = &[
+	// 2: optional deadlines and zoned date times
+	pass_through,
+	// 3: recurrence rules
+	pass_through,
+	// 4: tags, projects and priorities
+	pass_through,
+	// 5: parents and checklists
+	pass_through,
+	// 6: dependencies
+	pass_through,
+	// 7: attachments
+	pass_through,
+	// 8: time tracking events
+	pass_through,
+	// 9: reminders
+	pass_through,
+	// 10: statuses in place of `completed`
+	migrate_v9_to_v10,
+	// 11: saved view events
+	pass_through,
+	// 12: archive events
+	pass_through,
+	// 13: custom field events
+	pass_through,
+];
+
+/// For versions which only added new fields or events, so older data reads unchanged.
+/// The bump stops older clients from dropping what they don't know or failing to read it.
+fn pass_through(_data: &mut Value) -> Result<(), TasksError> {
+	Ok(())
+}
+
+/// Version 10 replaced `completed` with a status, so completed tasks become `Done` and the rest `Todo`.
+fn migrate_v9_to_v10(data: &mut Value) -> Result<(), TasksError> {
+	let events = match data.get_mut("events").and_then(Value::as_array_mut) {
+		Some(events) => events,
+		None => return Ok(()),
+	};
+	for event in events {
+		let data = match event.get_mut("data").and_then(Value::as_object_mut) {
+			Some(data) => data,
+			None => continue,
+		};
+		for (variant, task) in data.iter_mut() {
+			let task = match task.as_object_mut() {
+				Some(task) if variant == "CreateTask" || variant == "UpdateTask" => task,
+				_ => continue,
+			};
+			let completed = task
+				.remove("completed")
+				.and_then(|completed| completed.as_bool());
+			let status = if completed == Some(true) {
+				"Done"
+			} else {
+				"Todo"
+			};
+			task.insert("status".to_string(), Value::String(status.to_string()));
+		}
+	}
+	Ok(())
+}
+
+fn parse_version(data: &Value) -> Result<u32, TasksError> {
+	data.get("version")
+		.and_then(Value::as_str)
+		.and_then(|version| version.parse().ok())
+		.filter(|version| *version > 0)
+		.ok_or_else(|| TasksError::VersionError("Missing or invalid version".to_string()))
+}
+
+pub fn migrate(mut data: Value) -> Result<TasksData, TasksError> {
+	let version = parse_version(&data)?;
+	if version > SERIALIZATION_VERSION {
+		return Err(TasksError::VersionError(format!(
+			"Vault was saved in format {} but this version of the app only supports up to format {}",
+			version, SERIALIZATION_VERSION
+		)));
+	}
+
+	for migration in &MIGRATIONS[(version - 1) as usize..] {
+		migration(&mut data)?;
+	}
+	data["version"] = Value::String(SERIALIZATION_VERSION.to_string());
+
+	Ok(serde_json::from_value(data)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::attachment::Attachment;
+	use crate::event::{EventId, TaskEvent, TaskEventData};
+	use crate::field::{FieldDefinition, FieldType, FieldValue, Number};
+	use crate::recurrence::{Frequency, Recurrence, Weekday};
+	use crate::subtask::ChecklistItem;
+	use crate::task::{Deadline, Priority, Task, TaskId, ZonedDateTime};
+	use crate::timetracking::{TimeEntry, TimerEvent};
+	use crate::view::{SortField, SortKey, View};
+	use crate::workflow::Status;
+	use serde_json::json;
+	use std::collections::BTreeMap;
+
+	fn create_task(id: TaskId, description: &str) -> Task {
+		Task {
+			id,
+			description: description.to_string(),
+			..Default::default()
+		}
+	}
+
+	fn create(id: EventId, task: Task) -> TaskEvent {
+		TaskEvent {
+			id,
+			data: TaskEventData::CreateTask(task),
+		}
+	}
+
+	#[test]
+	fn test_migrations_reach_current_version() {
+		assert_eq!(MIGRATIONS.len() as u32, SERIALIZATION_VERSION - 1);
+	}
+
+	/// Each fixture is saved in the format of its version and uses what that version added.
+	#[test]
+	fn test_migrate_fixtures() {
+		let report = Task {
+			deadline: Some(Deadline::Date("2023-11-15".parse().unwrap())),
+			details: "Quarterly numbers".to_string(),
+			..create_task(1700000000000, "Write report")
+		};
+		let fixtures = vec![
+			(
+				include_str!("../fixtures/tasks_v1.json"),
+				vec![
+					create(1700000000000, report.clone()),
+					TaskEvent {
+						id: 1700000100000,
+						data: TaskEventData::UpdateTask(Task {
+							status: Status::Done,
+							..report
+						}),
+					},
+					TaskEvent {
+						id: 1700000200000,
+						data: TaskEventData::DeleteTask(1700000000000),
+					},
+				],
+			),
+			(
+				include_str!("../fixtures/tasks_v2.json"),
+				vec![
+					create(
+						1790000000000,
+						Task {
+							deadline: Some(Deadline::Zoned(ZonedDateTime {
+								local: "2026-10-21T15:00:00".parse().unwrap(),
+								time_zone: chrono_tz::Europe::London,
+							})),
+							..create_task(1790000000000, "Dentist")
+						},
+					),
+					create(
+						1790000100000,
+						Task {
+							details: "Someday".to_string(),
+							..create_task(1790000100000, "Learn the cello")
+						},
+					),
+				],
+			),
+			(
+				include_str!("../fixtures/tasks_v3.json"),
+				vec![create(
+					1820000000000,
+					Task {
+						deadline: Some(Deadline::Date("2026-11-02".parse().unwrap())),
+						recurrence: Some(Recurrence {
+							frequency: Frequency::Weekly,
+							interval: 2,
+							by_weekday: vec![Weekday::Monday],
+							end: None,
+						}),
+						..create_task(1820000000000, "Water the plants")
+					},
+				)],
+			),
+			(
+				include_str!("../fixtures/tasks_v4.json"),
+				vec![create(
+					1820000100000,
+					Task {
+						tags: vec!["errands".to_string(), "work".to_string()],
+						project: Some("Launch".to_string()),
+						priority: Priority::High,
+						..create_task(1820000100000, "Book the venue")
+					},
+				)],
+			),
+			(
+				include_str!("../fixtures/tasks_v5.json"),
+				vec![
+					create(1820000200000, create_task(1820000200000, "Move house")),
+					create(
+						1820000300000,
+						Task {
+							parent: Some(1820000200000),
+							checklist: vec![
+								ChecklistItem {
+									text: "Books".to_string(),
+									done: true,
+								},
+								ChecklistItem {
+									text: "Kitchen".to_string(),
+									done: false,
+								},
+							],
+							..create_task(1820000300000, "Pack")
+						},
+					),
+				],
+			),
+			(
+				include_str!("../fixtures/tasks_v6.json"),
+				vec![create(
+					1820000400000,
+					Task {
+						depends_on: vec![1820000300000],
+						..create_task(1820000400000, "Paint the walls")
+					},
+				)],
+			),
+			(
+				include_str!("../fixtures/tasks_v7.json"),
+				vec![create(
+					1820000500000,
+					Task {
+						attachments: vec![Attachment {
+							hash:
+								"9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+									.to_string(),
+							name: "receipt.pdf".to_string(),
+							size: 4,
+						}],
+						..create_task(1820000500000, "File the receipt")
+					},
+				)],
+			),
+			(
+				include_str!("../fixtures/tasks_v8.json"),
+				vec![
+					create(
+						1790000000000,
+						Task {
+							deadline: Some(Deadline::Date("2026-10-23".parse().unwrap())),
+							tags: vec!["billable".to_string()],
+							..create_task(1790000000000, "Client report")
+						},
+					),
+					TaskEvent {
+						id: 1790000100000,
+						data: TaskEventData::StartTimer(TimerEvent {
+							task_id: 1790000000000,
+							at: "2026-10-19T09:00:00Z".parse().unwrap(),
+						}),
+					},
+					TaskEvent {
+						id: 1790000200000,
+						data: TaskEventData::AddTimeEntry(TimeEntry {
+							task_id: 1790000000000,
+							start: "2026-10-18T14:00:00Z".parse().unwrap(),
+							end: "2026-10-18T15:30:00Z".parse().unwrap(),
+							note: "Call with client".to_string(),
+						}),
+					},
+				],
+			),
+			(
+				include_str!("../fixtures/tasks_v9.json"),
+				vec![create(
+					1820000600000,
+					Task {
+						reminders: vec!["2027-09-04T08:30:00Z".parse().unwrap()],
+						..create_task(1820000600000, "Call the plumber")
+					},
+				)],
+			),
+			(
+				include_str!("../fixtures/tasks_v10.json"),
+				vec![create(
+					1790000000000,
+					Task {
+						status: Status::Waiting,
+						..create_task(1790000000000, "Waiting on quote")
+					},
+				)],
+			),
+			(
+				include_str!("../fixtures/tasks_v11.json"),
+				vec![
+					TaskEvent {
+						id: 1790000000000,
+						data: TaskEventData::SaveView(View {
+							id: 1790000000000,
+							name: "Overdue".to_string(),
+							query: "overdue".to_string(),
+							sort: vec![SortKey {
+								field: SortField::Priority,
+								descending: true,
+							}],
+						}),
+					},
+					TaskEvent {
+						id: 1790000100000,
+						data: TaskEventData::DeleteView(1790000000000),
+					},
+				],
+			),
+			(
+				include_str!("../fixtures/tasks_v12.json"),
+				vec![TaskEvent {
+					id: 1800000000000,
+					data: TaskEventData::ArchiveTask(1700000000000),
+				}],
+			),
+			(
+				include_str!("../fixtures/tasks_v13.json"),
+				vec![
+					TaskEvent {
+						id: 1810000000000,
+						data: TaskEventData::SaveField(FieldDefinition {
+							id: 1810000000000,
+							name: "estimate".to_string(),
+							field_type: FieldType::Number,
+						}),
+					},
+					create(
+						1810000100000,
+						Task {
+							fields: BTreeMap::from([(
+								1810000000000,
+								FieldValue::Number(Number(2.5)),
+							)]),
+							..create_task(1810000100000, "Estimate me")
+						},
+					),
+					TaskEvent {
+						id: 1810000200000,
+						data: TaskEventData::DeleteField(1810000000000),
+					},
+				],
+			),
+		];
+		assert_eq!(fixtures.len() as u32, SERIALIZATION_VERSION);
+
+		for (version, (fixture, expected)) in (1..).zip(fixtures) {
+			let data: Value = serde_json::from_str(fixture).unwrap();
+			assert_eq!(parse_version(&data).unwrap(), version);
+			let tasks_data = migrate(data).unwrap();
+
+			assert_eq!(tasks_data.version, SERIALIZATION_VERSION.to_string());
+			assert_eq!(tasks_data.events, expected, "version {}", version);
+		}
+	}
+
+	#[test]
+	fn test_migrate_newer_version() {
+		let data = json!({
+			"version": (SERIALIZATION_VERSION + 1).to_string(),
+			"events": [],
+		});
+		assert!(matches!(migrate(data), Err(TasksError::VersionError(_))));
+	}
+
+	#[test]
+	fn test_migrate_invalid_version() {
+		for version in [json!(null), json!("0"), json!("one"), json!(1)] {
+			let data = json!({ "version": version, "events": [] });
+			assert!(matches!(migrate(data), Err(TasksError::VersionError(_))));
+		}
+	}
+}
@@ -0,0 +1,151 @@
+use crate::event::{EventId, TaskEvent, TaskEventData};
+use crate::reducer::Tasks;
+use crate::task::{Priority, Task};
+
+pub fn get_tasks_with_tag(tasks: &Tasks, tag: &str) -> Vec<Task> {
+	tasks
+		.values()
+		.filter(|task| task.tags.iter().any(|task_tag| task_tag == tag))
+		.cloned()
+		.collect()
+}
+
+pub fn get_tasks_in_project(tasks: &Tasks, project: &str) -> Vec<Task> {
+	tasks
+		.values()
+		.filter(|task| task.project.as_deref() == Some(project))
+		.cloned()
+		.collect()
+}
+
+pub fn get_tasks_with_priority(tasks: &Tasks, priority: Priority) -> Vec<Task> {
+	tasks
+		.values()
+		.filter(|task| task.priority == priority)
+		.cloned()
+		.collect()
+}
+
+pub fn get_all_tags(tasks: &Tasks) -> Vec<String> {
+	let mut tags: Vec<String> = tasks
+		.values()
+		.flat_map(|task| task.tags.iter().cloned())
+		.collect();
+	tags.sort();
+	tags.dedup();
+	tags
+}
+
+/// Renaming to a tag some tasks already have merges the two, so no task ends up with duplicates.
+pub fn create_rename_tag_events(
+	tasks: &Tasks,
+	from: &str,
+	to: &str,
+	first_id: EventId,
+) -> Vec<TaskEvent> {
+	if from == to {
+		return Vec::new();
+	}
+
+	get_tasks_with_tag(tasks, from)
+		.into_iter()
+		.zip(first_id..)
+		.map(|(task, id)| {
+			let mut tags: Vec<String> = Vec::with_capacity(task.tags.len());
+			for tag in task.tags.iter() {
+				let renamed = if tag == from { to } else { tag.as_str() };
+				if !tags.iter().any(|existing| existing == renamed) {
+					tags.push(renamed.to_string());
+				}
+			}
+			TaskEvent {
+				id,
+				data: TaskEventData::UpdateTask(Task { tags, ..task }),
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn create_task(id: u64, tags: &[&str]) -> Task {
+		Task {
+			id,
+			tags: tags.iter().map(|tag| tag.to_string()).collect(),
+			..Default::default()
+		}
+	}
+
+	fn create_tasks() -> Tasks {
+		Tasks::from([
+			(1, create_task(1, &["work", "urgent"])),
+			(
+				2,
+				Task {
+					project: Some("infra".to_string()),
+					priority: Priority::High,
+					..create_task(2, &["home"])
+				},
+			),
+			(3, create_task(3, &["urgent", "work", "call"])),
+		])
+	}
+
+	#[test]
+	fn test_get_tasks() {
+		let tasks = create_tasks();
+		let ids = |tasks: Vec<Task>| tasks.iter().map(|task| task.id).collect::<Vec<_>>();
+
+		assert_eq!(ids(get_tasks_with_tag(&tasks, "work")), vec![1, 3]);
+		assert_eq!(ids(get_tasks_in_project(&tasks, "infra")), vec![2]);
+		assert_eq!(
+			ids(get_tasks_with_priority(&tasks, Priority::Normal)),
+			vec![1, 3]
+		);
+		assert_eq!(get_all_tags(&tasks), vec!["call", "home", "urgent", "work"]);
+	}
+
+	#[test]
+	fn test_create_rename_tag_events() {
+		let tasks = create_tasks();
+
+		let events = create_rename_tag_events(&tasks, "work", "job", 10);
+
+		assert_eq!(
+			events,
+			vec![
+				TaskEvent {
+					id: 10,
+					data: TaskEventData::UpdateTask(create_task(1, &["job", "urgent"])),
+				},
+				TaskEvent {
+					id: 11,
+					data: TaskEventData::UpdateTask(create_task(3, &["urgent", "job", "call"])),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_create_rename_tag_events_merges_tags() {
+		let tasks = create_tasks();
+
+		let events = create_rename_tag_events(&tasks, "urgent", "work", 10);
+
+		assert_eq!(
+			events,
+			vec![
+				TaskEvent {
+					id: 10,
+					data: TaskEventData::UpdateTask(create_task(1, &["work"])),
+				},
+				TaskEvent {
+					id: 11,
+					data: TaskEventData::UpdateTask(create_task(3, &["work", "call"])),
+				},
+			]
+		);
+	}
+}
@@ -0,0 +1,447 @@
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::error::TasksError;
+use crate::event::{EventId, TaskEvent, TaskEventData};
+use crate::reducer::Tasks;
+use crate::subtask::ChecklistItem;
+use crate::task::{Deadline, Task, ZonedDateTime};
+use crate::workflow::Status;
+
+/// Gives up on monthly or yearly rules which never land on a valid date, eg the 30th of February
+const MAX_SKIPPED_PERIODS: u32 = 100;
+/// Larger intervals are rejected, keeping the date arithmetic well within range
+pub const MAX_INTERVAL: u32 = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Frequency {
+	Daily,
+	Weekly,
+	Monthly,
+	Yearly,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Weekday {
+	Monday,
+	Tuesday,
+	Wednesday,
+	Thursday,
+	Friday,
+	Saturday,
+	Sunday,
+}
+
+impl From<chrono::Weekday> for Weekday {
+	fn from(weekday: chrono::Weekday) -> Self {
+		match weekday {
+			chrono::Weekday::Mon => Weekday::Monday,
+			chrono::Weekday::Tue => Weekday::Tuesday,
+			chrono::Weekday::Wed => Weekday::Wednesday,
+			chrono::Weekday::Thu => Weekday::Thursday,
+			chrono::Weekday::Fri => Weekday::Friday,
+			chrono::Weekday::Sat => Weekday::Saturday,
+			chrono::Weekday::Sun => Weekday::Sunday,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RecurrenceEnd {
+	/// No occurrences after this date
+	Until(NaiveDate),
+	/// Occurrences remaining, including the current one
+	Count(u32),
+}
+
+/// A subset of the iCalendar RRULE: `FREQ`, `INTERVAL`, `BYDAY` for weekly rules, and `UNTIL` or `COUNT`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct Recurrence {
+	pub frequency: Frequency,
+	#[serde(default = "default_interval")]
+	pub interval: u32,
+	/// Only used by weekly rules; defaults to the weekday of the current occurrence
+	#[serde(default)]
+	pub by_weekday: Vec<Weekday>,
+	#[serde(default)]
+	pub end: Option<RecurrenceEnd>,
+}
+
+fn default_interval() -> u32 {
+	1
+}
+
+impl Recurrence {
+	fn get_next_weekly_date(&self, after: NaiveDate) -> Option<NaiveDate> {
+		let interval = Duration::weeks(self.interval.max(1) as i64);
+		if self.by_weekday.is_empty() {
+			return after.checked_add_signed(interval);
+		}
+
+		let days_from_monday = after.weekday().num_days_from_monday() as i64;
+		let week_start = after.checked_sub_signed(Duration::days(days_from_monday))?;
+		let is_included = |date: &NaiveDate| self.by_weekday.contains(&date.weekday().into());
+
+		// Later in the same week, otherwise the first matching day `interval` weeks on
+		let later_this_week = (days_from_monday + 1..7)
+			.filter_map(|offset| week_start.checked_add_signed(Duration::days(offset)))
+			.find(is_included);
+		if later_this_week.is_some() {
+			return later_this_week;
+		}
+		let next_week_start = week_start.checked_add_signed(interval)?;
+		(0..7)
+			.filter_map(|offset| next_week_start.checked_add_signed(Duration::days(offset)))
+			.find(is_included)
+			.or_else(|| after.checked_add_signed(interval))
+	}
+
+	/// Keeps the day of the month, skipping periods where it does not exist as RRULE does.
+	fn get_next_monthly_date(&self, after: NaiveDate, months_per_period: u32) -> Option<NaiveDate> {
+		let months = self.interval.max(1).checked_mul(months_per_period)?;
+		let first_of_month = after.with_day(1)?;
+		(1..=MAX_SKIPPED_PERIODS).find_map(|period| {
+			first_of_month
+				.checked_add_months(Months::new(months.checked_mul(period)?))
+				.and_then(|date| date.with_day(after.day()))
+		})
+	}
+
+	pub fn get_next_date(&self, after: NaiveDate) -> Option<NaiveDate> {
+		let next = match self.frequency {
+			Frequency::Daily => {
+				after.checked_add_signed(Duration::days(self.interval.max(1) as i64))
+			}
+			Frequency::Weekly => self.get_next_weekly_date(after),
+			Frequency::Monthly => self.get_next_monthly_date(after, 1),
+			Frequency::Yearly => self.get_next_monthly_date(after, 12),
+		}?;
+
+		match self.end {
+			Some(RecurrenceEnd::Until(until)) if next > until => None,
+			Some(RecurrenceEnd::Count(count)) if count <= 1 => None,
+			_ => Some(next),
+		}
+	}
+
+	fn advance(&self) -> Self {
+		let end = match self.end {
+			Some(RecurrenceEnd::Count(count)) => Some(RecurrenceEnd::Count(count - 1)),
+			end => end,
+		};
+		Recurrence {
+			end,
+			..self.clone()
+		}
+	}
+}
+
+pub fn validate_recurrence(recurrence: &Recurrence) -> Result<(), TasksError> {
+	if recurrence.interval > MAX_INTERVAL {
+		return Err(TasksError::InvalidEventError(format!(
+			"Recurrence interval must be at most {}",
+			MAX_INTERVAL
+		)));
+	}
+	Ok(())
+}
+
+/// Undated tasks recur relative to `today`. Reminders move by as many days as the deadline.
+pub fn create_next_occurrence(task: &Task, id: u64, today: NaiveDate) -> Option<Task> {
+	let recurrence = task.recurrence.as_ref()?;
+	let (deadline, days) = match task.deadline {
+		None => {
+			let next = recurrence.get_next_date(today)?;
+			(Deadline::Date(next), next - today)
+		}
+		Some(Deadline::Date(date)) => {
+			let next = recurrence.get_next_date(date)?;
+			(Deadline::Date(next), next - date)
+		}
+		Some(Deadline::Zoned(zoned)) => {
+			let date = zoned.local.date();
+			let next = recurrence.get_next_date(date)?;
+			let deadline = Deadline::Zoned(ZonedDateTime {
+				local: next.and_time(zoned.local.time()),
+				..zoned
+			});
+			(deadline, next - date)
+		}
+	};
+	let reminders = task
+		.reminders
+		.iter()
+		.map(|reminder| reminder.checked_add_signed(days))
+		.collect::<Option<Vec<_>>>()?;
+
+	// Every field is listed so that new ones have to decide whether they carry over
+	Some(Task {
+		id,
+		description: task.description.clone(),
+		deadline: Some(deadline),
+		details: task.details.clone(),
+		status: Status::Todo,
+		status_history: Vec::new(),
+		recurrence: Some(recurrence.advance()),
+		tags: task.tags.clone(),
+		project: task.project.clone(),
+		priority: task.priority,
+		parent: task.parent,
+		checklist: task
+			.checklist
+			.iter()
+			.map(|item| ChecklistItem {
+				done: false,
+				..item.clone()
+			})
+			.collect(),
+		// Prerequisites were met for this occurrence and are not repeated with it
+		depends_on: Vec::new(),
+		// Shared reference material such as a form to fill in each time. Only the hashes are copied,
+		// the encrypted files are shared by content
+		attachments: task.attachments.clone(),
+		reminders,
+		fields: task.fields.clone(),
+	})
+}
+
+/// Returns a `CreateTask` event for the next occurrence if `event` marks a recurring task as done.
+pub fn get_follow_up_events(
+	tasks: &Tasks,
+	event: &TaskEvent,
+	next_id: EventId,
+	today: NaiveDate,
+) -> Vec<TaskEvent> {
+	let task = match &event.data {
+		TaskEventData::UpdateTask(task) => task,
+		_ => return Vec::new(),
+	};
+	let was_done = tasks
+		.get(&task.id)
+		.map_or(true, |existing| existing.status == Status::Done);
+	if task.status != Status::Done || was_done {
+		return Vec::new();
+	}
+
+	create_next_occurrence(task, next_id, today)
+		.map(|next_task| TaskEvent {
+			id: next_id,
+			data: TaskEventData::CreateTask(next_task),
+		})
+		.into_iter()
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::{DateTime, Utc};
+
+	fn date(date: &str) -> NaiveDate {
+		date.parse().unwrap()
+	}
+
+	fn recurrence(frequency: Frequency, interval: u32) -> Recurrence {
+		Recurrence {
+			frequency,
+			interval,
+			by_weekday: Vec::new(),
+			end: None,
+		}
+	}
+
+	#[test]
+	fn test_get_next_date_daily() {
+		let every_three_days = recurrence(Frequency::Daily, 3);
+		assert_eq!(
+			every_three_days.get_next_date(date("2026-12-30")),
+			Some(date("2027-01-02"))
+		);
+	}
+
+	#[test]
+	fn test_get_next_date_weekly() {
+		let fortnightly = recurrence(Frequency::Weekly, 2);
+		assert_eq!(
+			fortnightly.get_next_date(date("2026-10-21")),
+			Some(date("2026-11-04"))
+		);
+	}
+
+	#[test]
+	fn test_get_next_date_weekly_by_weekday() {
+		let every_other_tuesday_and_friday = Recurrence {
+			by_weekday: vec![Weekday::Friday, Weekday::Tuesday],
+			..recurrence(Frequency::Weekly, 2)
+		};
+		// Tuesday -> Friday of the same week
+		assert_eq!(
+			every_other_tuesday_and_friday.get_next_date(date("2026-10-20")),
+			Some(date("2026-10-23"))
+		);
+		// Friday -> Tuesday two weeks on
+		assert_eq!(
+			every_other_tuesday_and_friday.get_next_date(date("2026-10-23")),
+			Some(date("2026-11-03"))
+		);
+	}
+
+	#[test]
+	fn test_get_next_date_monthly_skips_missing_days() {
+		let monthly = recurrence(Frequency::Monthly, 1);
+		assert_eq!(
+			monthly.get_next_date(date("2026-01-15")),
+			Some(date("2026-02-15"))
+		);
+		assert_eq!(
+			monthly.get_next_date(date("2026-01-31")),
+			Some(date("2026-03-31"))
+		);
+	}
+
+	#[test]
+	fn test_get_next_date_yearly_on_leap_day() {
+		let yearly = recurrence(Frequency::Yearly, 1);
+		assert_eq!(
+			yearly.get_next_date(date("2024-02-29")),
+			Some(date("2028-02-29"))
+		);
+	}
+
+	#[test]
+	fn test_get_next_date_with_end() {
+		let until = Recurrence {
+			end: Some(RecurrenceEnd::Until(date("2026-10-31"))),
+			..recurrence(Frequency::Weekly, 1)
+		};
+		assert_eq!(
+			until.get_next_date(date("2026-10-21")),
+			Some(date("2026-10-28"))
+		);
+		assert_eq!(until.get_next_date(date("2026-10-28")), None);
+
+		let last = Recurrence {
+			end: Some(RecurrenceEnd::Count(1)),
+			..recurrence(Frequency::Daily, 1)
+		};
+		assert_eq!(last.get_next_date(date("2026-10-21")), None);
+	}
+
+	fn create_recurring_task(id: u64, status: Status) -> Task {
+		Task {
+			id,
+			description: "Weekly review".to_string(),
+			deadline: Some(Deadline::Zoned(ZonedDateTime {
+				local: "2026-10-23T16:00:00".parse().unwrap(),
+				time_zone: chrono_tz::Europe::London,
+			})),
+			status,
+			recurrence: Some(Recurrence {
+				end: Some(RecurrenceEnd::Count(3)),
+				..recurrence(Frequency::Weekly, 1)
+			}),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_get_follow_up_events() {
+		let tasks = Tasks::from([(1, create_recurring_task(1, Status::Todo))]);
+		let event = TaskEvent {
+			id: 10,
+			data: TaskEventData::UpdateTask(create_recurring_task(1, Status::Done)),
+		};
+
+		let follow_up_events = get_follow_up_events(&tasks, &event, 11, date("2026-10-21"));
+
+		let expected = Task {
+			id: 11,
+			deadline: Some(Deadline::Zoned(ZonedDateTime {
+				local: "2026-10-30T16:00:00".parse().unwrap(),
+				time_zone: chrono_tz::Europe::London,
+			})),
+			recurrence: Some(Recurrence {
+				end: Some(RecurrenceEnd::Count(2)),
+				..recurrence(Frequency::Weekly, 1)
+			}),
+			..create_recurring_task(1, Status::Todo)
+		};
+		assert_eq!(
+			follow_up_events,
+			vec![TaskEvent {
+				id: 11,
+				data: TaskEventData::CreateTask(expected),
+			}]
+		);
+	}
+
+	#[test]
+	fn test_get_follow_up_events_ignores_already_done_tasks() {
+		let tasks = Tasks::from([(1, create_recurring_task(1, Status::Done))]);
+		let event = TaskEvent {
+			id: 10,
+			data: TaskEventData::UpdateTask(create_recurring_task(1, Status::Done)),
+		};
+
+		assert!(get_follow_up_events(&tasks, &event, 11, date("2026-10-21")).is_empty());
+	}
+
+	#[test]
+	fn test_create_next_occurrence_of_undated_task() {
+		let task = Task {
+			deadline: None,
+			..create_recurring_task(1, Status::Done)
+		};
+
+		let next_task = create_next_occurrence(&task, 2, date("2026-10-21")).unwrap();
+
+		assert_eq!(next_task.deadline, Some(Deadline::Date(date("2026-10-28"))));
+	}
+
+	#[test]
+	fn test_create_next_occurrence_resets_progress() {
+		let task = Task {
+			checklist: vec![ChecklistItem {
+				text: "Inbox zero".to_string(),
+				done: true,
+			}],
+			depends_on: vec![5],
+			reminders: vec!["2026-10-23T08:00:00Z".parse().unwrap()],
+			..create_recurring_task(1, Status::Done)
+		};
+
+		let next_task = create_next_occurrence(&task, 2, date("2026-10-21")).unwrap();
+
+		assert_eq!(
+			next_task.checklist,
+			vec![ChecklistItem {
+				text: "Inbox zero".to_string(),
+				done: false,
+			}]
+		);
+		assert!(next_task.depends_on.is_empty());
+		assert_eq!(
+			next_task.reminders,
+			vec!["2026-10-30T08:00:00Z".parse::<DateTime<Utc>>().unwrap()]
+		);
+	}
+
+	#[test]
+	fn test_get_next_date_out_of_range() {
+		assert_eq!(
+			recurrence(Frequency::Daily, u32::MAX).get_next_date(NaiveDate::MAX),
+			None
+		);
+		assert_eq!(
+			recurrence(Frequency::Weekly, 1).get_next_date(NaiveDate::MAX),
+			None
+		);
+		assert_eq!(
+			recurrence(Frequency::Yearly, u32::MAX).get_next_date(date("2026-10-21")),
+			None
+		);
+		assert!(validate_recurrence(&recurrence(Frequency::Daily, MAX_INTERVAL)).is_ok());
+		assert!(validate_recurrence(&recurrence(Frequency::Daily, MAX_INTERVAL + 1)).is_err());
+	}
+}
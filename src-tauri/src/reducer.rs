@@ -0,0 +1,594 @@
+use std::collections::BTreeMap;
+
+use crate::dependency::would_create_dependency_cycle;
+use crate::error::TasksError;
+use crate::event::{get_event_time, TaskEvent, TaskEventData};
+use crate::field::{apply_field_event, validate_field, validate_task_fields, Fields};
+use crate::recurrence::validate_recurrence;
+use crate::subtask::would_create_cycle;
+use crate::task::{Task, TaskId};
+use crate::timetracking::{TimeEntry, TimerEvent};
+use crate::view::{validate_view, validate_views_with_fields, Views};
+use crate::workflow::{StatusChange, Workflow};
+
+pub type Tasks = BTreeMap<TaskId, Task>;
+
+/// Rejects events which would leave the tasks inconsistent, before they are saved.
+pub fn validate_event(
+	tasks: &Tasks,
+	event: &TaskEvent,
+	workflow: &Workflow,
+	fields: &Fields,
+	views: &Views,
+) -> Result<(), TasksError> {
+	match &event.data {
+		TaskEventData::CreateTask(task) | TaskEventData::UpdateTask(task) => {
+			let from = match (&event.data, tasks.get(&task.id)) {
+				(TaskEventData::UpdateTask(_), Some(existing)) => existing.status,
+				// New tasks can start in any state the workflow offers
+				_ => task.status,
+			};
+			if !workflow.states.contains(&task.status)
+				|| !workflow.can_transition(from, task.status)
+			{
+				return Err(TasksError::InvalidEventError(format!(
+					"Task {} cannot move from {:?} to {:?}",
+					task.id, from, task.status
+				)));
+			}
+			if let Some(parent) = task.parent {
+				if !tasks.contains_key(&parent) {
+					return Err(TasksError::InvalidEventError(format!(
+						"Parent task {} does not exist",
+						parent
+					)));
+				}
+				if would_create_cycle(tasks, task.id, Some(parent)) {
+					return Err(TasksError::InvalidEventError(format!(
+						"Task {} cannot be nested under its own subtask {}",
+						task.id, parent
+					)));
+				}
+			}
+			if let Some(missing) = task.depends_on.iter().find(|id| !tasks.contains_key(id)) {
+				return Err(TasksError::InvalidEventError(format!(
+					"Prerequisite task {} does not exist",
+					missing
+				)));
+			}
+			if would_create_dependency_cycle(tasks, task.id, &task.depends_on) {
+				return Err(TasksError::InvalidEventError(format!(
+					"Task {} cannot depend on its own dependents",
+					task.id
+				)));
+			}
+			if let Some(recurrence) = &task.recurrence {
+				validate_recurrence(recurrence)?;
+			}
+			validate_task_fields(task, fields)
+		}
+		TaskEventData::StartTimer(TimerEvent { task_id, .. })
+		| TaskEventData::AddTimeEntry(TimeEntry { task_id, .. })
+			if !tasks.contains_key(task_id) =>
+		{
+			Err(TasksError::InvalidEventError(format!(
+				"Task {} does not exist",
+				task_id
+			)))
+		}
+		TaskEventData::AddTimeEntry(entry) if entry.end < entry.start => Err(
+			TasksError::InvalidEventError("Time entry ends before it starts".to_string()),
+		),
+		TaskEventData::SaveView(view) => validate_view(view, fields),
+		TaskEventData::SaveField(field) => {
+			validate_field(field, fields, tasks)?;
+			validate_views_with_fields(views, &get_changed_fields(fields, event))
+		}
+		TaskEventData::DeleteField(_) => {
+			validate_views_with_fields(views, &get_changed_fields(fields, event))
+		}
+		TaskEventData::ArchiveTask(task_id) => match tasks.get(task_id) {
+			None => Err(TasksError::InvalidEventError(format!(
+				"Task {} does not exist",
+				task_id
+			))),
+			Some(task) if !task.status.is_closed() => Err(TasksError::InvalidEventError(format!(
+				"Task {} is still open",
+				task_id
+			))),
+			Some(_) if is_referenced(tasks, *task_id) => {
+				Err(TasksError::InvalidEventError(format!(
+					"Task {} is a parent or prerequisite of another task",
+					task_id
+				)))
+			}
+			Some(_) => Ok(()),
+		},
+		_ => Ok(()),
+	}
+}
+
+fn get_changed_fields(fields: &Fields, event: &TaskEvent) -> Fields {
+	let mut changed = fields.clone();
+	apply_field_event(&mut changed, event);
+	changed
+}
+
+/// Whether another task is nested under or depends on the task.
+pub fn is_referenced(tasks: &Tasks, task_id: TaskId) -> bool {
+	tasks
+		.values()
+		.any(|task| task.parent == Some(task_id) || task.depends_on.contains(&task_id))
+}
+
+/// Events merged from other replicas skip validation, so a parent or prerequisites which would form a cycle are dropped instead.
+fn sanitize(tasks: &Tasks, task: &Task) -> Task {
+	let mut task = task.clone();
+	if would_create_cycle(tasks, task.id, task.parent) {
+		task.parent = tasks.get(&task.id).and_then(|existing| existing.parent);
+		if would_create_cycle(tasks, task.id, task.parent) {
+			task.parent = None;
+		}
+	}
+	let task_id = task.id;
+	task.depends_on
+		.retain(|id| !would_create_dependency_cycle(tasks, task_id, &[*id]));
+	task
+}
+
+/// Event ids are timestamps, so they double as the time of each status change.
+/// A task restored from the archive keeps the history it was created with.
+fn record_status_change(tasks: &Tasks, mut task: Task, event: &TaskEvent) -> Task {
+	if let Some(existing) = tasks.get(&task.id) {
+		task.status_history = existing.status_history.clone();
+	}
+	let last_status = task.status_history.last().map(|change| change.status);
+	if last_status != Some(task.status) {
+		task.status_history.push(StatusChange {
+			status: task.status,
+			at: get_event_time(event.id),
+		});
+	}
+	task
+}
+
+/// Parents and prerequisites which no longer exist are dropped so a removed task can be recreated.
+pub fn create_restored_task(tasks: &Tasks, removed: &Task) -> Task {
+	let mut task = removed.clone();
+	task.parent = task.parent.filter(|parent| tasks.contains_key(parent));
+	task.depends_on
+		.retain(|task_id| tasks.contains_key(task_id));
+	task
+}
+
+/// Mirrors the frontend reducer so the backend can answer queries about the current tasks.
+pub fn apply_event(tasks: &mut Tasks, event: &TaskEvent) {
+	match &event.data {
+		TaskEventData::CreateTask(task) => {
+			let task = record_status_change(tasks, sanitize(tasks, task), event);
+			tasks.insert(task.id, task);
+		}
+		TaskEventData::UpdateTask(task) => {
+			if tasks.contains_key(&task.id) {
+				let task = record_status_change(tasks, sanitize(tasks, task), event);
+				tasks.insert(task.id, task);
+			}
+		}
+		TaskEventData::DeleteTask(task_id) => {
+			// Subtasks move up to the deleted task's parent rather than being deleted with it
+			if let Some(deleted) = tasks.remove(task_id) {
+				for task in tasks.values_mut() {
+					if task.parent == Some(*task_id) {
+						task.parent = deleted.parent;
+					}
+					task.depends_on.retain(|id| id != task_id);
+				}
+			}
+		}
+		TaskEventData::ArchiveTask(task_id) => {
+			tasks.remove(task_id);
+		}
+		TaskEventData::DeleteField(field_id) => {
+			for task in tasks.values_mut() {
+				task.fields.remove(field_id);
+			}
+		}
+		// Time tracking, views and field definitions do not change the tasks themselves
+		TaskEventData::StartTimer(_)
+		| TaskEventData::StopTimer(_)
+		| TaskEventData::AddTimeEntry(_)
+		| TaskEventData::SaveView(_)
+		| TaskEventData::DeleteView(_)
+		| TaskEventData::SaveField(_) => {}
+	}
+}
+
+/// Expects events in order.
+pub fn materialize(events: &[TaskEvent]) -> Tasks {
+	let mut tasks = Tasks::new();
+	for event in events {
+		apply_event(&mut tasks, event);
+	}
+	tasks
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::workflow::Status;
+
+	fn create_task(id: TaskId, description: &str) -> Task {
+		Task {
+			id,
+			description: description.to_string(),
+			deadline: Default::default(),
+			details: Default::default(),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_materialize() {
+		let events = vec![
+			TaskEvent {
+				id: 1,
+				data: TaskEventData::CreateTask(create_task(1, "Task 1")),
+			},
+			TaskEvent {
+				id: 2,
+				data: TaskEventData::CreateTask(create_task(2, "Task 2")),
+			},
+			TaskEvent {
+				id: 3,
+				data: TaskEventData::UpdateTask(create_task(1, "Updated")),
+			},
+			TaskEvent {
+				id: 4,
+				data: TaskEventData::DeleteTask(2),
+			},
+			TaskEvent {
+				id: 5,
+				data: TaskEventData::UpdateTask(create_task(2, "Deleted")),
+			},
+		];
+
+		let tasks = materialize(&events);
+
+		assert_eq!(tasks.len(), 1);
+		assert_eq!(
+			tasks[&1],
+			Task {
+				status_history: vec![StatusChange {
+					status: Status::Todo,
+					at: get_event_time(1),
+				}],
+				..create_task(1, "Updated")
+			}
+		);
+	}
+
+	fn create_subtask(id: TaskId, parent: TaskId) -> Task {
+		Task {
+			parent: Some(parent),
+			..create_task(id, "Subtask")
+		}
+	}
+
+	#[test]
+	fn test_validate_event() {
+		let tasks = materialize(&[
+			TaskEvent {
+				id: 1,
+				data: TaskEventData::CreateTask(create_task(1, "Parent")),
+			},
+			TaskEvent {
+				id: 2,
+				data: TaskEventData::CreateTask(create_subtask(2, 1)),
+			},
+		]);
+		let update = |task: Task| TaskEvent {
+			id: 3,
+			data: TaskEventData::UpdateTask(task),
+		};
+		let workflow = Workflow::default();
+		let fields = Fields::new();
+		let views = Views::new();
+
+		assert!(validate_event(
+			&tasks,
+			&update(create_subtask(2, 1)),
+			&workflow,
+			&fields,
+			&views
+		)
+		.is_ok());
+		assert!(validate_event(
+			&tasks,
+			&update(create_subtask(1, 2)),
+			&workflow,
+			&fields,
+			&views
+		)
+		.is_err());
+		assert!(validate_event(
+			&tasks,
+			&update(create_subtask(2, 9)),
+			&workflow,
+			&fields,
+			&views
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn test_apply_event_drops_cyclic_parent() {
+		let tasks = materialize(&[
+			TaskEvent {
+				id: 1,
+				data: TaskEventData::CreateTask(create_task(1, "Parent")),
+			},
+			TaskEvent {
+				id: 2,
+				data: TaskEventData::CreateTask(create_subtask(2, 1)),
+			},
+			TaskEvent {
+				id: 3,
+				data: TaskEventData::UpdateTask(create_subtask(1, 2)),
+			},
+		]);
+
+		assert_eq!(tasks[&1].parent, None);
+		assert_eq!(tasks[&2].parent, Some(1));
+	}
+
+	#[test]
+	fn test_apply_delete_event_reparents_subtasks() {
+		let tasks = materialize(&[
+			TaskEvent {
+				id: 1,
+				data: TaskEventData::CreateTask(create_task(1, "Grandparent")),
+			},
+			TaskEvent {
+				id: 2,
+				data: TaskEventData::CreateTask(create_subtask(2, 1)),
+			},
+			TaskEvent {
+				id: 3,
+				data: TaskEventData::CreateTask(create_subtask(3, 2)),
+			},
+			TaskEvent {
+				id: 4,
+				data: TaskEventData::DeleteTask(2),
+			},
+		]);
+
+		assert_eq!(tasks.keys().cloned().collect::<Vec<_>>(), vec![1, 3]);
+		assert_eq!(tasks[&3].parent, Some(1));
+	}
+
+	fn create_dependent_task(id: TaskId, depends_on: Vec<TaskId>) -> Task {
+		Task {
+			depends_on,
+			..create_task(id, "Dependent")
+		}
+	}
+
+	#[test]
+	fn test_validate_event_rejects_dependency_cycles() {
+		let tasks = materialize(&[
+			TaskEvent {
+				id: 1,
+				data: TaskEventData::CreateTask(create_task(1, "First")),
+			},
+			TaskEvent {
+				id: 2,
+				data: TaskEventData::CreateTask(create_dependent_task(2, vec![1])),
+			},
+		]);
+		let update = |task: Task| TaskEvent {
+			id: 3,
+			data: TaskEventData::UpdateTask(task),
+		};
+		let workflow = Workflow::default();
+		let fields = Fields::new();
+		let views = Views::new();
+
+		assert!(validate_event(
+			&tasks,
+			&update(create_dependent_task(2, vec![1])),
+			&workflow,
+			&fields,
+			&views
+		)
+		.is_ok());
+		assert!(validate_event(
+			&tasks,
+			&update(create_dependent_task(1, vec![2])),
+			&workflow,
+			&fields,
+			&views
+		)
+		.is_err());
+		assert!(validate_event(
+			&tasks,
+			&update(create_dependent_task(1, vec![9])),
+			&workflow,
+			&fields,
+			&views
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn test_apply_event_drops_cyclic_and_deleted_dependencies() {
+		let tasks = materialize(&[
+			TaskEvent {
+				id: 1,
+				data: TaskEventData::CreateTask(create_task(1, "First")),
+			},
+			TaskEvent {
+				id: 2,
+				data: TaskEventData::CreateTask(create_dependent_task(2, vec![1])),
+			},
+			TaskEvent {
+				id: 3,
+				data: TaskEventData::CreateTask(create_task(3, "Third")),
+			},
+			TaskEvent {
+				id: 4,
+				data: TaskEventData::UpdateTask(create_dependent_task(1, vec![2, 3])),
+			},
+			TaskEvent {
+				id: 5,
+				data: TaskEventData::DeleteTask(3),
+			},
+		]);
+
+		assert!(tasks[&1].depends_on.is_empty());
+		assert_eq!(tasks[&2].depends_on, vec![1]);
+	}
+
+	#[test]
+	fn test_validate_event_checks_status_transitions() {
+		let tasks = materialize(&[TaskEvent {
+			id: 1,
+			data: TaskEventData::CreateTask(Task {
+				status: Status::Done,
+				..create_task(1, "Done")
+			}),
+		}]);
+		let update = |status: Status| TaskEvent {
+			id: 2,
+			data: TaskEventData::UpdateTask(Task {
+				status,
+				..create_task(1, "Done")
+			}),
+		};
+		let workflow = Workflow::default();
+		let fields = Fields::new();
+		let views = Views::new();
+
+		assert!(validate_event(&tasks, &update(Status::Todo), &workflow, &fields, &views).is_ok());
+		assert!(validate_event(
+			&tasks,
+			&update(Status::InProgress),
+			&workflow,
+			&fields,
+			&views
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn test_apply_event_records_status_changes() {
+		let with_status = |status: Status| Task {
+			status,
+			status_history: Vec::new(),
+			..create_task(1, "Task")
+		};
+		let tasks = materialize(&[
+			TaskEvent {
+				id: 1790000000000,
+				data: TaskEventData::CreateTask(with_status(Status::Todo)),
+			},
+			TaskEvent {
+				id: 1790000060000,
+				data: TaskEventData::UpdateTask(with_status(Status::InProgress)),
+			},
+			TaskEvent {
+				id: 1790000120000,
+				data: TaskEventData::UpdateTask(Task {
+					description: "Renamed".to_string(),
+					..with_status(Status::InProgress)
+				}),
+			},
+		]);
+
+		assert_eq!(
+			tasks[&1].status_history,
+			vec![
+				StatusChange {
+					status: Status::Todo,
+					at: get_event_time(1790000000000),
+				},
+				StatusChange {
+					status: Status::InProgress,
+					at: get_event_time(1790000060000),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_archive_task() {
+		let events = [
+			TaskEvent {
+				id: 1790000000000,
+				data: TaskEventData::CreateTask(Task {
+					status: Status::Done,
+					..create_task(1, "Done")
+				}),
+			},
+			TaskEvent {
+				id: 1790000060000,
+				data: TaskEventData::CreateTask(create_task(2, "Open")),
+			},
+			TaskEvent {
+				id: 1790000120000,
+				data: TaskEventData::CreateTask(Task {
+					depends_on: vec![1],
+					..create_task(3, "Dependent")
+				}),
+			},
+		];
+		let mut tasks = materialize(&events);
+		let archive = |task_id: TaskId| TaskEvent {
+			id: 1790000180000,
+			data: TaskEventData::ArchiveTask(task_id),
+		};
+		let workflow = Workflow::default();
+		let fields = Fields::new();
+		let views = Views::new();
+
+		assert!(validate_event(&tasks, &archive(1), &workflow, &fields, &views).is_err());
+		assert!(validate_event(&tasks, &archive(2), &workflow, &fields, &views).is_err());
+		assert!(validate_event(&tasks, &archive(4), &workflow, &fields, &views).is_err());
+
+		tasks.remove(&3);
+		assert!(validate_event(&tasks, &archive(1), &workflow, &fields, &views).is_ok());
+		let archived = tasks[&1].clone();
+		apply_event(&mut tasks, &archive(1));
+		assert!(!tasks.contains_key(&1));
+
+		// Restoring keeps the history recorded before the task was archived
+		apply_event(
+			&mut tasks,
+			&TaskEvent {
+				id: 1790000240000,
+				data: TaskEventData::CreateTask(archived.clone()),
+			},
+		);
+		assert_eq!(tasks[&1].status_history, archived.status_history);
+	}
+
+	#[test]
+	fn test_create_restored_task() {
+		let tasks = Tasks::from([(1, create_task(1, "Kept"))]);
+		let removed = Task {
+			parent: Some(2),
+			depends_on: vec![1, 3],
+			..create_task(4, "Removed")
+		};
+
+		let restored = create_restored_task(&tasks, &removed);
+
+		assert_eq!(
+			restored,
+			Task {
+				parent: None,
+				depends_on: vec![1],
+				..removed
+			}
+		);
+	}
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::api::notification::Notification;
+use tauri::{AppHandle, Manager};
+
+use crate::config::{AppConfig, Config};
+use crate::crypto::{decrypt, encrypt, EncryptionKey};
+use crate::error::TasksError;
+use crate::event::EventStore;
+use crate::fs::{read_file_into_buffer, write_buffer_to_file};
+use crate::reducer::Tasks;
+use crate::storage::acquire_lock;
+use crate::task::TaskId;
+use crate::util::get_fired_reminders_path;
+use crate::vault::VaultId;
+
+pub const REMINDER_DUE_EVENT: &str = "reminder-due";
+pub const REMINDER_INTERVAL: Duration = Duration::from_secs(15);
+/// Reminders missed while the app was closed or locked are only caught up on if they are this recent
+pub const MISSED_REMINDER_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub trait Clock {
+	fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> DateTime<Utc> {
+		Utc::now()
+	}
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DueReminder {
+	pub task_id: TaskId,
+	pub description: String,
+	pub at: DateTime<Utc>,
+}
+
+pub type FiredReminders = HashSet<(TaskId, DateTime<Utc>)>;
+
+/// Remembers which reminders have fired, so each fires once even across lock and unlock.
+/// Saving them with `save_fired_reminders` carries that across restarts.
+pub struct ReminderScheduler<C: Clock> {
+	clock: C,
+	fired: FiredReminders,
+}
+
+impl<C: Clock> ReminderScheduler<C> {
+	pub fn new(clock: C) -> Self {
+		Self::with_fired(clock, FiredReminders::new())
+	}
+
+	pub fn with_fired(clock: C, fired: FiredReminders) -> Self {
+		ReminderScheduler { clock, fired }
+	}
+
+	/// Returns reminders of open tasks which are due and have not fired yet, oldest first.
+	pub fn poll(&mut self, tasks: &Tasks) -> Vec<DueReminder> {
+		let now = self.clock.now();
+		let earliest = now - chrono::Duration::from_std(MISSED_REMINDER_WINDOW).unwrap();
+		// Reminders older than the window can't fire again, so there is no need to remember them
+		self.fired.retain(|(_, at)| earliest <= *at);
+		let mut due: Vec<DueReminder> = tasks
+			.values()
+			.filter(|task| !task.status.is_closed())
+			.flat_map(|task| {
+				task.reminders
+					.iter()
+					.filter(|at| earliest <= **at && **at <= now)
+					.map(move |at| DueReminder {
+						task_id: task.id,
+						description: task.description.clone(),
+						at: *at,
+					})
+			})
+			.filter(|reminder| self.fired.insert((reminder.task_id, reminder.at)))
+			.collect();
+		due.sort_by_key(|reminder| reminder.at);
+		due
+	}
+}
+
+/// Encrypted, since the reminder times and task ids would otherwise be readable. Nothing has fired yet
+/// if the file is missing or unreadable.
+pub fn load_fired_reminders(config: &Config, encryption_key: &EncryptionKey) -> FiredReminders {
+	read_file_into_buffer(&get_fired_reminders_path(&config.context, config.vault_id))
+		.and_then(|encrypted| decrypt(&encrypted, &encryption_key.0.lock().unwrap()))
+		.and_then(|json| serde_json::from_str(&json).map_err(TasksError::from))
+		.unwrap_or_default()
+}
+
+pub fn save_fired_reminders(
+	config: &Config,
+	fired: &FiredReminders,
+	encryption_key: &EncryptionKey,
+) -> Result<(), TasksError> {
+	let json = serde_json::to_string(fired)?;
+	let encrypted = encrypt(json.as_bytes(), &encryption_key.0.lock().unwrap())?;
+	let _lock = acquire_lock(&config.context)?;
+	write_buffer_to_file(
+		&get_fired_reminders_path(&config.context, config.vault_id),
+		&encrypted,
+	)
+}
+
+fn notify(app_handle: &AppHandle, reminder: &DueReminder) {
+	let identifier = app_handle.config().tauri.bundle.identifier.clone();
+	let _ = Notification::new(identifier)
+		.title("Reminder")
+		.body(&reminder.description)
+		.show();
+}
+
+/// Checks for due reminders while unlocked. Those which came due while locked fire on unlock.
+pub fn watch_reminders<C: Clock + Send + 'static>(app_handle: AppHandle, clock: C) {
+	thread::spawn(move || {
+		let mut scheduler = ReminderScheduler::new(clock);
+		// The fired reminders are loaded on unlock, since they can only be decrypted then
+		let mut loaded_vault: Option<VaultId> = None;
+		loop {
+			thread::sleep(REMINDER_INTERVAL);
+
+			let encryption_key = app_handle.state::<EncryptionKey>();
+			if encryption_key.is_locked() {
+				loaded_vault = None;
+				continue;
+			}
+			let config = app_handle
+				.state::<AppConfig>()
+				.config
+				.lock()
+				.unwrap()
+				.clone();
+			if loaded_vault != Some(config.vault_id) {
+				scheduler.fired = load_fired_reminders(&config, &encryption_key);
+				loaded_vault = Some(config.vault_id);
+			}
+			let due = scheduler.poll(&app_handle.state::<EventStore>().tasks());
+			if due.is_empty() {
+				continue;
+			}
+
+			let _ = save_fired_reminders(&config, &scheduler.fired, &encryption_key);
+
+			for reminder in &due {
+				notify(&app_handle, reminder);
+			}
+			let _ = app_handle.emit_all(REMINDER_DUE_EVENT, due);
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::context::AppContext;
+	use crate::task::Task;
+	use crate::workflow::Status;
+	use std::cell::Cell;
+	use std::rc::Rc;
+	use std::sync::Mutex;
+	use tempfile::tempdir;
+
+	#[derive(Clone)]
+	struct FakeClock(Rc<Cell<DateTime<Utc>>>);
+
+	impl FakeClock {
+		fn set(&self, time: &str) {
+			self.0.set(datetime(time));
+		}
+	}
+
+	impl Clock for FakeClock {
+		fn now(&self) -> DateTime<Utc> {
+			self.0.get()
+		}
+	}
+
+	fn datetime(time: &str) -> DateTime<Utc> {
+		time.parse().unwrap()
+	}
+
+	fn create_tasks(reminders: &[&str], status: Status) -> Tasks {
+		Tasks::from([(
+			1,
+			Task {
+				id: 1,
+				description: "Call the bank".to_string(),
+				status,
+				reminders: reminders.iter().map(|time| datetime(time)).collect(),
+				..Default::default()
+			},
+		)])
+	}
+
+	fn fired_at(reminders: Vec<DueReminder>) -> Vec<DateTime<Utc>> {
+		reminders.into_iter().map(|reminder| reminder.at).collect()
+	}
+
+	#[test]
+	fn test_poll_fires_each_reminder_once() {
+		let clock = FakeClock(Rc::new(Cell::new(datetime("2026-10-19T09:00:00Z"))));
+		let mut scheduler = ReminderScheduler::new(clock.clone());
+		let tasks = create_tasks(
+			&["2026-10-19T10:00:00Z", "2026-10-19T12:00:00Z"],
+			Status::Todo,
+		);
+
+		assert!(scheduler.poll(&tasks).is_empty());
+
+		clock.set("2026-10-19T10:00:00Z");
+		assert_eq!(
+			fired_at(scheduler.poll(&tasks)),
+			vec![datetime("2026-10-19T10:00:00Z")]
+		);
+		assert!(scheduler.poll(&tasks).is_empty());
+	}
+
+	#[test]
+	fn test_poll_catches_up_on_missed_reminders() {
+		let clock = FakeClock(Rc::new(Cell::new(datetime("2026-10-19T09:00:00Z"))));
+		let mut scheduler = ReminderScheduler::new(clock.clone());
+		let tasks = create_tasks(
+			&[
+				"2026-10-17T09:00:00Z",
+				"2026-10-18T20:00:00Z",
+				"2026-10-19T08:00:00Z",
+			],
+			Status::Todo,
+		);
+
+		// Too old to be worth a notification
+		assert_eq!(
+			fired_at(scheduler.poll(&tasks)),
+			vec![
+				datetime("2026-10-18T20:00:00Z"),
+				datetime("2026-10-19T08:00:00Z"),
+			]
+		);
+	}
+
+	#[test]
+	fn test_poll_forgets_reminders_older_than_the_window() {
+		let clock = FakeClock(Rc::new(Cell::new(datetime("2026-10-19T09:00:00Z"))));
+		let mut scheduler = ReminderScheduler::new(clock.clone());
+		let tasks = create_tasks(&["2026-10-19T08:00:00Z"], Status::Todo);
+
+		assert_eq!(scheduler.poll(&tasks).len(), 1);
+		assert_eq!(scheduler.fired.len(), 1);
+
+		clock.set("2026-10-20T08:30:00Z");
+		assert!(scheduler.poll(&tasks).is_empty());
+		assert!(scheduler.fired.is_empty());
+	}
+
+	#[test]
+	fn test_poll_skips_closed_tasks() {
+		let clock = FakeClock(Rc::new(Cell::new(datetime("2026-10-19T09:00:00Z"))));
+		let mut scheduler = ReminderScheduler::new(clock);
+		let tasks = create_tasks(&["2026-10-19T08:00:00Z"], Status::Cancelled);
+
+		assert!(scheduler.poll(&tasks).is_empty());
+	}
+
+	#[test]
+	fn test_fired_reminders_survive_a_restart() {
+		let dir = tempdir().unwrap();
+		let config = Config::new(&AppContext::new(dir.path()));
+		let encryption_key = EncryptionKey(Mutex::new([1; 32]));
+		let clock = FakeClock(Rc::new(Cell::new(datetime("2026-10-19T09:00:00Z"))));
+		let tasks = create_tasks(&["2026-10-19T08:00:00Z"], Status::Todo);
+		let mut scheduler = ReminderScheduler::new(clock.clone());
+		assert_eq!(scheduler.poll(&tasks).len(), 1);
+
+		save_fired_reminders(&config, &scheduler.fired, &encryption_key).unwrap();
+		let fired = load_fired_reminders(&config, &encryption_key);
+		let mut restarted = ReminderScheduler::with_fired(clock, fired);
+
+		assert!(restarted.poll(&tasks).is_empty());
+		assert!(load_fired_reminders(&config, &EncryptionKey(Mutex::new([2; 32]))).is_empty());
+	}
+}
@@ -0,0 +1,203 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::slice;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::crypto::EncryptionKey;
+use crate::error::TasksError;
+use crate::event::{hashmap_to_sorted_vec, EventId, EventStore, TaskEvent};
+use crate::fs::read_file_into_buffer;
+use crate::storage::{
+	acquire_lock, merge_events_from_files, process_event_data, write_events_to_paths,
+};
+use crate::util::get_tasks_paths;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaStatus {
+	pub path: PathBuf,
+	pub exists: bool,
+	pub modified: Option<DateTime<Utc>>,
+	pub decrypts: bool,
+	pub event_count: Option<usize>,
+	/// Events in the merged set which this replica does not contain yet
+	pub missing_event_ids: Vec<EventId>,
+}
+
+pub fn get_replica_status(
+	path: &Path,
+	encryption_key: &EncryptionKey,
+	merged_events: &HashMap<EventId, TaskEvent>,
+) -> ReplicaStatus {
+	let modified = path
+		.metadata()
+		.and_then(|metadata| metadata.modified())
+		.ok()
+		.map(DateTime::<Utc>::from);
+	let file_events = read_file_into_buffer(&path.to_path_buf())
+		.and_then(|encrypted_data| process_event_data(&encrypted_data, encryption_key))
+		.ok();
+
+	let missing_event_ids = match &file_events {
+		Some(file_events) => {
+			let file_event_ids: HashSet<EventId> =
+				file_events.iter().map(|event| event.id).collect();
+			let mut missing: Vec<EventId> = merged_events
+				.keys()
+				.filter(|id| !file_event_ids.contains(id))
+				.cloned()
+				.collect();
+			missing.sort();
+			missing
+		}
+		None => Vec::new(),
+	};
+
+	ReplicaStatus {
+		path: path.to_path_buf(),
+		exists: path.exists(),
+		modified,
+		decrypts: file_events.is_some(),
+		event_count: file_events.as_ref().map(Vec::len),
+		missing_event_ids,
+	}
+}
+
+pub fn get_replica_statuses(
+	config: &Config,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Vec<ReplicaStatus> {
+	let merged_events = event_store.events.lock().unwrap();
+	get_tasks_paths(config)
+		.iter()
+		.map(|path| get_replica_status(path, encryption_key, &merged_events))
+		.collect()
+}
+
+/// Overwrites a single replica with the merged events, keeping any it has which the store lacks.
+pub fn resync_replica(
+	config: &Config,
+	path: PathBuf,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<(), TasksError> {
+	if !get_tasks_paths(config).contains(&path) {
+		return Err(TasksError::UnknownError(format!(
+			"Not a configured replica: {}",
+			path.display()
+		)));
+	}
+
+	let _lock = acquire_lock(&config.context)?;
+	// An unreadable replica is exactly what resyncing is meant to repair
+	if let Ok(added) = merge_events_from_files(slice::from_ref(&path), encryption_key, event_store)
+	{
+		event_store.unannounced.lock().unwrap().extend(added);
+	}
+	let events = hashmap_to_sorted_vec(&event_store.events.lock().unwrap());
+	write_events_to_paths(vec![path], events, encryption_key)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::context::AppContext;
+	use crate::crypto::ENCRYPTION_KEY_SIZE;
+	use crate::event::TaskEventData;
+	use crate::fs::write_buffer_to_file;
+	use crate::storage::save_events_to_paths;
+	use crate::util::get_lock_path;
+	use std::sync::Mutex;
+	use tempfile::tempdir;
+
+	fn create_event(id: EventId) -> TaskEvent {
+		TaskEvent {
+			id,
+			data: TaskEventData::DeleteTask(id),
+		}
+	}
+
+	fn create_merged_events(ids: &[EventId]) -> HashMap<EventId, TaskEvent> {
+		ids.iter().map(|id| (*id, create_event(*id))).collect()
+	}
+
+	#[test]
+	fn test_get_replica_status_missing_file() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("tasks");
+		let encryption_key = EncryptionKey(Mutex::new([1u8; ENCRYPTION_KEY_SIZE]));
+
+		let status = get_replica_status(&path, &encryption_key, &create_merged_events(&[1]));
+
+		assert!(!status.exists);
+		assert!(!status.decrypts);
+		assert_eq!(status.modified, None);
+		assert_eq!(status.event_count, None);
+		assert!(status.missing_event_ids.is_empty());
+	}
+
+	#[test]
+	fn test_get_replica_status_lagging_file() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("tasks");
+		let encryption_key = EncryptionKey(Mutex::new([1u8; ENCRYPTION_KEY_SIZE]));
+		save_events_to_paths(
+			&AppContext::new(dir.path()),
+			vec![path.clone()],
+			vec![create_event(1)],
+			&encryption_key,
+		)
+		.unwrap();
+
+		let status = get_replica_status(&path, &encryption_key, &create_merged_events(&[1, 3, 2]));
+
+		assert!(status.exists);
+		assert!(status.decrypts);
+		assert!(status.modified.is_some());
+		assert_eq!(status.event_count, Some(1));
+		assert_eq!(status.missing_event_ids, vec![2, 3]);
+	}
+
+	#[test]
+	fn test_get_replica_status_undecryptable_file() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("tasks");
+		let encryption_key = EncryptionKey(Mutex::new([1u8; ENCRYPTION_KEY_SIZE]));
+		write_buffer_to_file(&path, b"not encrypted with this key").unwrap();
+
+		let status = get_replica_status(&path, &encryption_key, &create_merged_events(&[1]));
+
+		assert!(status.exists);
+		assert!(!status.decrypts);
+		assert_eq!(status.event_count, None);
+	}
+
+	#[test]
+	fn test_resync_replica() {
+		let dir = tempdir().unwrap();
+		let config = Config {
+			dropbox_enabled: true,
+			..Config::new(&AppContext::new(dir.path()))
+		};
+		let encryption_key = EncryptionKey(Mutex::new([1u8; ENCRYPTION_KEY_SIZE]));
+		let event_store = EventStore::new();
+		event_store
+			.events
+			.lock()
+			.unwrap()
+			.extend(create_merged_events(&[1]));
+		let path = get_tasks_paths(&config)[1].clone();
+		write_buffer_to_file(&path, b"half synced").unwrap();
+
+		resync_replica(&config, path.clone(), &encryption_key, &event_store).unwrap();
+
+		let status = get_replica_status(&path, &encryption_key, &create_merged_events(&[1]));
+		assert!(status.decrypts);
+		assert!(status.missing_event_ids.is_empty());
+		assert!(!get_lock_path(&config.context).exists());
+	}
+}
@@ -0,0 +1,376 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::event::{TaskEvent, TaskEventData};
+use crate::reducer::Tasks;
+use crate::task::{Task, TaskId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Field {
+	Description,
+	Details,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Clause {
+	Term {
+		field: Option<Field>,
+		text: String,
+		prefix: bool,
+	},
+	Phrase {
+		field: Option<Field>,
+		words: Vec<String>,
+	},
+}
+
+/// Lowercased runs of letters and digits.
+pub fn tokenize(text: &str) -> Vec<String> {
+	text.split(|c: char| !c.is_alphanumeric())
+		.filter(|word| !word.is_empty())
+		.map(str::to_lowercase)
+		.collect()
+}
+
+fn parse_field(name: &str) -> Option<Field> {
+	match name {
+		"description" => Some(Field::Description),
+		"details" => Some(Field::Details),
+		_ => None,
+	}
+}
+
+/// Parses space-separated clauses, which must all match: `word`, `prefix*`, `"a phrase"`,
+/// optionally scoped with `description:` or `details:`.
+pub fn parse_search_query(query: &str) -> Vec<Clause> {
+	let mut clauses = Vec::new();
+	let mut rest = query.trim_start();
+	while !rest.is_empty() {
+		let mut field = None;
+		if let Some((name, after)) = rest.split_once(':') {
+			if let Some(parsed) = parse_field(name) {
+				field = Some(parsed);
+				rest = after;
+			}
+		}
+
+		if let Some(quoted) = rest.strip_prefix('"') {
+			let (phrase, after) = quoted.split_once('"').unwrap_or((quoted, ""));
+			let words = tokenize(phrase);
+			if !words.is_empty() {
+				clauses.push(Clause::Phrase { field, words });
+			}
+			rest = after;
+		} else {
+			let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+			let (word, after) = rest.split_at(end);
+			let prefix = word.ends_with('*');
+			let words = tokenize(word);
+			let last = words.len().saturating_sub(1);
+			for (i, text) in words.into_iter().enumerate() {
+				clauses.push(Clause::Term {
+					field,
+					text,
+					// Only the last part of eg `e-mai*` is a prefix
+					prefix: prefix && i == last,
+				});
+			}
+			rest = after;
+		}
+		rest = rest.trim_start();
+	}
+	clauses
+}
+
+/// Positions of each term in each field of each task.
+type Postings = BTreeMap<String, HashMap<(TaskId, Field), Vec<usize>>>;
+
+/// An inverted index over the decrypted tasks. It only ever lives in memory, so nothing
+/// searchable is written to disk unencrypted.
+#[derive(Default)]
+pub struct SearchIndex {
+	postings: Postings,
+	terms_by_task: HashMap<TaskId, HashSet<String>>,
+}
+
+impl SearchIndex {
+	pub fn new() -> Self {
+		Default::default()
+	}
+
+	pub fn build(tasks: &Tasks) -> Self {
+		let mut index = SearchIndex::new();
+		for task in tasks.values() {
+			index.index_task(task);
+		}
+		index
+	}
+
+	pub fn clear(&mut self) {
+		self.postings.clear();
+		self.terms_by_task.clear();
+	}
+
+	pub fn remove_task(&mut self, task_id: TaskId) {
+		for term in self.terms_by_task.remove(&task_id).unwrap_or_default() {
+			if let Some(entries) = self.postings.get_mut(&term) {
+				entries.retain(|(id, _), _| *id != task_id);
+				if entries.is_empty() {
+					self.postings.remove(&term);
+				}
+			}
+		}
+	}
+
+	pub fn index_task(&mut self, task: &Task) {
+		self.remove_task(task.id);
+		let mut terms = HashSet::new();
+		for (field, text) in [
+			(Field::Description, &task.description),
+			(Field::Details, &task.details),
+		] {
+			for (position, term) in tokenize(text).into_iter().enumerate() {
+				self.postings
+					.entry(term.clone())
+					.or_default()
+					.entry((task.id, field))
+					.or_default()
+					.push(position);
+				terms.insert(term);
+			}
+		}
+		self.terms_by_task.insert(task.id, terms);
+	}
+
+	/// Updates the index after `events` have been applied to `tasks`.
+	pub fn apply_events(&mut self, tasks: &Tasks, events: &[TaskEvent]) {
+		for event in events {
+			let task_id = match &event.data {
+				TaskEventData::CreateTask(task) | TaskEventData::UpdateTask(task) => task.id,
+				TaskEventData::DeleteTask(task_id) | TaskEventData::ArchiveTask(task_id) => {
+					*task_id
+				}
+				_ => continue,
+			};
+			match tasks.get(&task_id) {
+				Some(task) => self.index_task(task),
+				None => self.remove_task(task_id),
+			}
+		}
+	}
+
+	/// Positions of terms matching `text` per task field, merged across terms for prefixes.
+	fn find_term(
+		&self,
+		field: Option<Field>,
+		text: &str,
+		prefix: bool,
+	) -> HashMap<(TaskId, Field), Vec<usize>> {
+		let entries: Vec<&HashMap<(TaskId, Field), Vec<usize>>> = if prefix {
+			self.postings
+				.range(text.to_string()..)
+				.take_while(|(term, _)| term.starts_with(text))
+				.map(|(_, entries)| entries)
+				.collect()
+		} else {
+			self.postings.get(text).into_iter().collect()
+		};
+
+		let mut matches: HashMap<(TaskId, Field), Vec<usize>> = HashMap::new();
+		for entry in entries {
+			for ((task_id, entry_field), positions) in entry {
+				if field.map_or(true, |field| field == *entry_field) {
+					matches
+						.entry((*task_id, *entry_field))
+						.or_default()
+						.extend(positions);
+				}
+			}
+		}
+		matches
+	}
+
+	/// Number of matches of the clause per task.
+	fn match_clause(&self, clause: &Clause) -> HashMap<TaskId, usize> {
+		let mut counts: HashMap<TaskId, usize> = HashMap::new();
+		match clause {
+			Clause::Term {
+				field,
+				text,
+				prefix,
+			} => {
+				for ((task_id, _), positions) in self.find_term(*field, text, *prefix) {
+					*counts.entry(task_id).or_default() += positions.len();
+				}
+			}
+			Clause::Phrase { field, words } => {
+				let word_matches: Vec<HashMap<(TaskId, Field), Vec<usize>>> = words
+					.iter()
+					.map(|word| self.find_term(*field, word, false))
+					.collect();
+				for ((task_id, task_field), starts) in &word_matches[0] {
+					let count = starts
+						.iter()
+						.filter(|start| {
+							word_matches[1..].iter().enumerate().all(|(i, matches)| {
+								matches
+									.get(&(*task_id, *task_field))
+									.map_or(false, |positions| {
+										positions.contains(&(**start + i + 1))
+									})
+							})
+						})
+						.count();
+					if count > 0 {
+						*counts.entry(*task_id).or_default() += count;
+					}
+				}
+			}
+		}
+		counts
+	}
+
+	/// Ids of tasks matching every clause, best matches first.
+	pub fn search(&self, clauses: &[Clause]) -> Vec<TaskId> {
+		let mut scores: Option<HashMap<TaskId, usize>> = None;
+		for clause in clauses {
+			let counts = self.match_clause(clause);
+			scores = Some(match scores {
+				None => counts,
+				Some(scores) => scores
+					.into_iter()
+					.filter_map(|(task_id, score)| {
+						counts.get(&task_id).map(|count| (task_id, score + count))
+					})
+					.collect(),
+			});
+		}
+
+		let mut ranked: Vec<(TaskId, usize)> = scores.unwrap_or_default().into_iter().collect();
+		ranked
+			.sort_by(|(a_id, a_score), (b_id, b_score)| b_score.cmp(a_score).then(a_id.cmp(b_id)));
+		ranked.into_iter().map(|(task_id, _)| task_id).collect()
+	}
+}
+
+/// Built when the events are loaded and wiped on lock.
+#[derive(Default)]
+pub struct SearchStore {
+	pub index: Mutex<SearchIndex>,
+}
+
+impl SearchStore {
+	pub fn new() -> Self {
+		Default::default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn create_tasks() -> Tasks {
+		let create_task = |id: TaskId, description: &str, details: &str| Task {
+			id,
+			description: description.to_string(),
+			details: details.to_string(),
+			..Default::default()
+		};
+		Tasks::from([
+			(
+				1,
+				create_task(1, "Email the landlord", "About the boiler repair"),
+			),
+			(2, create_task(2, "Repair the bike", "Email email email")),
+			(
+				3,
+				create_task(3, "Boiler service", "Landlord pays for repairs"),
+			),
+		])
+	}
+
+	fn search(index: &SearchIndex, query: &str) -> Vec<TaskId> {
+		index.search(&parse_search_query(query))
+	}
+
+	#[test]
+	fn test_parse_search_query() {
+		assert_eq!(
+			parse_search_query(r#"details:"Boiler Repair" rep* e-mail"#),
+			vec![
+				Clause::Phrase {
+					field: Some(Field::Details),
+					words: vec!["boiler".to_string(), "repair".to_string()],
+				},
+				Clause::Term {
+					field: None,
+					text: "rep".to_string(),
+					prefix: true,
+				},
+				Clause::Term {
+					field: None,
+					text: "e".to_string(),
+					prefix: false,
+				},
+				Clause::Term {
+					field: None,
+					text: "mail".to_string(),
+					prefix: false,
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_search_terms_and_prefixes() {
+		let index = SearchIndex::build(&create_tasks());
+
+		assert_eq!(search(&index, "landlord"), vec![1, 3]);
+		assert_eq!(search(&index, "repair"), vec![1, 2]);
+		assert_eq!(search(&index, "repair*"), vec![1, 2, 3]);
+		// More matches rank higher
+		assert_eq!(search(&index, "email"), vec![2, 1]);
+		assert_eq!(search(&index, "landlord boiler"), vec![1, 3]);
+		assert!(search(&index, "plumber").is_empty());
+	}
+
+	#[test]
+	fn test_search_phrases_and_fields() {
+		let index = SearchIndex::build(&create_tasks());
+
+		assert_eq!(search(&index, r#""boiler repair""#), vec![1]);
+		assert!(search(&index, r#""repair boiler""#).is_empty());
+		assert_eq!(search(&index, "description:boiler"), vec![3]);
+		assert_eq!(search(&index, "details:email"), vec![2]);
+	}
+
+	#[test]
+	fn test_apply_events() {
+		let mut tasks = create_tasks();
+		let mut index = SearchIndex::build(&tasks);
+
+		let updated = Task {
+			description: "Call the plumber".to_string(),
+			..tasks[&3].clone()
+		};
+		tasks.insert(3, updated.clone());
+		tasks.remove(&1);
+		index.apply_events(
+			&tasks,
+			&[
+				TaskEvent {
+					id: 10,
+					data: TaskEventData::UpdateTask(updated),
+				},
+				TaskEvent {
+					id: 11,
+					data: TaskEventData::DeleteTask(1),
+				},
+			],
+		);
+
+		assert_eq!(search(&index, "plumber"), vec![3]);
+		assert!(search(&index, "boiler").is_empty());
+		assert_eq!(search(&index, "landlord"), vec![3]);
+	}
+}
@@ -1,17 +1,35 @@
+use std::fs::remove_file;
+use std::iter;
 use std::path::PathBuf;
+use std::slice;
 
+use chrono::Local;
 use serde::{Deserialize, Serialize};
-use tauri::State;
 
-use crate::config::{Config, SERIALIZATION_VERSION};
+use crate::archive::{load_archive, write_archive};
+use crate::attachment::reencrypt_attachments;
+use crate::config::{Config, ConfigData, SERIALIZATION_VERSION};
+use crate::context::AppContext;
 use crate::crypto::{
 	decrypt, derive_key, encrypt, generate_random_bytes, EncryptionKey, Salt, ENCRYPTION_KEY_SIZE,
 	SALT_SIZE,
 };
 use crate::error::TasksError;
-use crate::event::{hashmap_to_sorted_vec, EventStore, TaskEvent};
+use crate::event::{
+	get_next_event_id, hashmap_to_sorted_vec, merge_events, EventId, EventStore, TaskEvent,
+};
+use crate::field::{apply_field_event, materialize_fields};
 use crate::fs::{read_file_into_buffer, write_buffer_to_file};
-use crate::util::{find_first_existing_file, get_config_path, get_salt_paths, get_tasks_paths};
+use crate::lockfile::VaultLock;
+use crate::migration::migrate;
+use crate::recurrence::get_follow_up_events;
+use crate::reducer::{apply_event, materialize, validate_event};
+use crate::util::{
+	find_first_existing_file, get_archive_paths, get_attachments_dirs, get_config_path,
+	get_lock_path, get_salt_paths, get_tasks_conflict_copy_paths, get_tasks_paths,
+};
+use crate::vault::VaultId;
+use crate::view::{apply_view_event, materialize_views};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TasksData {
@@ -23,13 +41,20 @@ pub fn check_exists(config: &Config) -> Result<bool, TasksError> {
 	Ok(get_tasks_paths(config).iter().any(|path| path.exists()))
 }
 
-fn save_data_to_files(data: &[u8], paths: Vec<PathBuf>) -> Result<(), TasksError> {
+pub fn save_data_to_files(data: &[u8], paths: Vec<PathBuf>) -> Result<(), TasksError> {
 	paths
 		.iter()
 		.try_for_each(|path| write_buffer_to_file(path, &data))
 }
 
+/// Held while reading replicas to merge into a write, and during the write itself,
+/// so that other instances can't write in between. Not reentrant, see `VaultLock`.
+pub fn acquire_lock(context: &AppContext) -> Result<VaultLock, TasksError> {
+	VaultLock::acquire(&get_lock_path(context))
+}
+
 pub fn save_salt(config: &Config, salt: &[u8; SALT_SIZE]) -> Result<(), TasksError> {
+	let _lock = acquire_lock(&config.context)?;
 	save_data_to_files(salt, get_salt_paths(config))
 }
 
@@ -43,28 +68,168 @@ pub fn create_new_salt(config: &Config) -> Result<Salt, TasksError> {
 pub fn load_salt(config: &Config) -> Result<[u8; SALT_SIZE], TasksError> {
 	let salt_paths = get_salt_paths(config);
 	let salt = match find_first_existing_file(&salt_paths) {
-		Some(salt_path) => {
-			let salt_vec = read_file_into_buffer(&salt_path)?;
-			salt_vec
-				.try_into()
-				.map_err(|_| TasksError::UnknownError("Invalid salt size".to_string()))?
-		}
+		Some(salt_path) => read_salt(&salt_path)?,
 		None => create_new_salt(config)?,
 	};
 	Ok(salt)
 }
 
+fn read_salt(path: &PathBuf) -> Result<Salt, TasksError> {
+	read_file_into_buffer(path)?
+		.try_into()
+		.map_err(|_| TasksError::UnknownError("Invalid salt size".to_string()))
+}
+
+/// Returns each distinct salt found across locations, in order of preference.
+pub fn load_salts(config: &Config) -> Result<Vec<Salt>, TasksError> {
+	let mut salts: Vec<Salt> = Vec::new();
+	for salt_path in get_salt_paths(config) {
+		if salt_path.exists() {
+			let salt = read_salt(&salt_path)?;
+			if !salts.contains(&salt) {
+				salts.push(salt);
+			}
+		}
+	}
+	Ok(salts)
+}
+
+/// Picks the salt whose key decrypts the earliest replica it can, collecting the events from every replica
+/// that any of the candidate salts can decrypt. With no replica to check the password against,
+/// the most preferred salt is kept.
+pub fn resolve_divergent_salts(
+	password: &str,
+	salts: &[Salt],
+	tasks_paths: &[PathBuf],
+) -> Result<(Salt, Vec<TaskEvent>), TasksError> {
+	let mut candidates = Vec::with_capacity(salts.len());
+	for salt in salts {
+		let candidate_key = EncryptionKey::default();
+		derive_key(password, salt, &mut candidate_key.0.lock().unwrap())?;
+		candidates.push((*salt, candidate_key));
+	}
+
+	let mut resolved_salt = None;
+	let mut events = Vec::new();
+	let mut any_read = false;
+	for tasks_path in tasks_paths {
+		let encrypted_data = match read_file_into_buffer(tasks_path) {
+			Ok(encrypted_data) => encrypted_data,
+			Err(_) => continue,
+		};
+		any_read = true;
+		for (salt, candidate_key) in &candidates {
+			if let Ok(file_events) = process_event_data(&encrypted_data, candidate_key) {
+				resolved_salt.get_or_insert(*salt);
+				events.extend(file_events);
+				break;
+			}
+		}
+	}
+
+	match (resolved_salt, salts.first()) {
+		(Some(salt), _) => Ok((salt, events)),
+		(None, Some(salt)) if !any_read => Ok((*salt, events)),
+		_ => Err(TasksError::CryptoError("Incorrect password".to_string())),
+	}
+}
+
+/// Derives the key, first reconciling replicas which ended up encrypted under different salts.
+pub fn unlock(
+	config: &Config,
+	password: &str,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<(), TasksError> {
+	let salts = load_salts(config)?;
+	match salts.as_slice() {
+		[] => {
+			let salt = create_new_salt(config)?;
+			derive_key(password, &salt, &mut encryption_key.0.lock().unwrap())
+		}
+		[salt] => {
+			if get_salt_paths(config).iter().any(|path| !path.exists()) {
+				save_salt(config, salt)?;
+			}
+			derive_key(password, salt, &mut encryption_key.0.lock().unwrap())
+		}
+		_ => {
+			let _lock = acquire_lock(&config.context)?;
+			reconcile_divergent_salts(config, password, &salts, encryption_key, event_store)?;
+			Ok(())
+		}
+	}
+}
+
+/// Settles on one salt and rewrites every replica under it, returning the events merged in.
+/// The caller must hold the lock.
+fn reconcile_divergent_salts(
+	config: &Config,
+	password: &str,
+	salts: &[Salt],
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let (salt, events) = resolve_divergent_salts(password, salts, &get_tasks_paths(config))?;
+	derive_key(password, &salt, &mut encryption_key.0.lock().unwrap())?;
+	save_data_to_files(&salt, get_salt_paths(config))?;
+
+	let mut all_events = event_store.events.lock().unwrap();
+	let added = merge_events(&mut all_events, events);
+	write_events(config, hashmap_to_sorted_vec(&all_events), encryption_key)?;
+	Ok(added)
+}
+
+/// Brings locations which were just enabled up to date, merging in whatever they already hold rather
+/// than overwriting it. A location encrypted under a different salt is reconciled as on unlock,
+/// which needs the password.
+pub fn sync_replicas(
+	config: &Config,
+	password: Option<&str>,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<(), TasksError> {
+	let _lock = acquire_lock(&config.context)?;
+	let salts = load_salts(config)?;
+	match (salts.as_slice(), password) {
+		([], _) => Err(TasksError::CryptoError("Vault has no salt".to_string())),
+		([salt], _) => {
+			save_data_to_files(salt, get_salt_paths(config))?;
+			merge_replicas(config, encryption_key, event_store);
+			let events = hashmap_to_sorted_vec(&event_store.events.lock().unwrap());
+			write_events(config, events, encryption_key)
+		}
+		(_, Some(password)) => {
+			let added =
+				reconcile_divergent_salts(config, password, &salts, encryption_key, event_store)?;
+			event_store.unannounced.lock().unwrap().extend(added);
+			Ok(())
+		}
+		(_, None) => Err(TasksError::CryptoError(
+			"Password is required to merge a location encrypted separately".to_string(),
+		)),
+	}
+}
+
 pub fn save_config(config: &Config) -> Result<(), TasksError> {
 	let config_data = serde_json::to_string(&config)?;
-	save_data_to_files(&config_data.into_bytes(), vec![get_config_path()])
+	let _lock = acquire_lock(&config.context)?;
+	save_data_to_files(
+		&config_data.into_bytes(),
+		vec![get_config_path(&config.context, config.vault_id)],
+	)
 }
 
-pub fn load_config() -> Config {
-	read_file_into_buffer(&get_config_path())
+pub fn load_config(context: &AppContext, vault_id: VaultId) -> Config {
+	read_file_into_buffer(&get_config_path(context, vault_id))
 		.and_then(|config_json| {
-			serde_json::from_slice::<Config>(&config_json).map_err(TasksError::from)
+			serde_json::from_slice::<ConfigData>(&config_json).map_err(TasksError::from)
+		})
+		.map(|config_data| config_data.into_config(context, vault_id))
+		.unwrap_or_else(|_| Config {
+			vault_id,
+			..Config::new(context)
 		})
-		.unwrap_or_default()
 }
 
 fn encrypt_then_save(
@@ -77,62 +242,257 @@ fn encrypt_then_save(
 	Ok(())
 }
 
-pub fn save_events(
-	config: &Config,
+/// The caller must hold the lock.
+pub fn write_events_to_paths(
+	paths: Vec<PathBuf>,
 	events: Vec<TaskEvent>,
-	encryption_key: &State<EncryptionKey>,
+	encryption_key: &EncryptionKey,
 ) -> Result<(), TasksError> {
 	let tasks_data = TasksData {
 		version: SERIALIZATION_VERSION.to_string(),
 		events,
 	};
 	let serialized_tasks_data = serde_json::to_string(&tasks_data)?.into_bytes();
-	encrypt_then_save(
-		&serialized_tasks_data,
-		encryption_key,
-		get_tasks_paths(config),
-	)
+	encrypt_then_save(&serialized_tasks_data, encryption_key, paths)
 }
 
+pub fn save_events_to_paths(
+	context: &AppContext,
+	paths: Vec<PathBuf>,
+	events: Vec<TaskEvent>,
+	encryption_key: &EncryptionKey,
+) -> Result<(), TasksError> {
+	let _lock = acquire_lock(context)?;
+	write_events_to_paths(paths, events, encryption_key)
+}
+
+/// The caller must hold the lock.
+fn write_events(
+	config: &Config,
+	events: Vec<TaskEvent>,
+	encryption_key: &EncryptionKey,
+) -> Result<(), TasksError> {
+	write_events_to_paths(get_tasks_paths(config), events, encryption_key)
+}
+
+pub fn save_events(
+	config: &Config,
+	events: Vec<TaskEvent>,
+	encryption_key: &EncryptionKey,
+) -> Result<(), TasksError> {
+	let _lock = acquire_lock(&config.context)?;
+	write_events(config, events, encryption_key)
+}
+
+/// Returns any events generated in response, such as the next occurrence of a completed recurring task.
 pub fn save_event(
 	config: &Config,
 	event: TaskEvent,
-	encryption_key: &State<EncryptionKey>,
-	event_store: &State<EventStore>,
-) -> Result<(), TasksError> {
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	save_events_batch(config, vec![event], encryption_key, event_store)
+}
+
+/// Validates and stores the events with a single write, returning any follow-up events as `save_event` does.
+/// If any event is invalid or the write fails, the store is left as it was.
+pub fn save_events_batch(
+	config: &Config,
+	mut batch: Vec<TaskEvent>,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let _lock = acquire_lock(&config.context)?;
+	merge_replicas(config, encryption_key, event_store);
+
+	// Validate in the order the events will be materialized
+	batch.sort();
 	let mut events = event_store.events.lock().unwrap();
-	events.insert(event.id, event);
 	let sorted_events = hashmap_to_sorted_vec(&events);
-	save_events(config, sorted_events, encryption_key)
+	let mut tasks = materialize(&sorted_events);
+	let mut fields = materialize_fields(&sorted_events);
+	let mut views = materialize_views(&sorted_events);
+	let today = Local::now().date_naive();
+	let mut next_id = tasks
+		.keys()
+		.max()
+		.map_or(0, |task_id| task_id + 1)
+		.max(get_next_event_id(&events))
+		.max(batch.last().map_or(0, |event| event.id + 1));
+
+	let mut new_events = Vec::new();
+	let mut follow_up_events = Vec::new();
+	for event in batch {
+		validate_event(&tasks, &event, &config.workflow, &fields, &views)?;
+		let event_follow_ups = get_follow_up_events(&tasks, &event, next_id, today);
+		next_id += event_follow_ups.len() as EventId;
+		for new_event in iter::once(&event).chain(&event_follow_ups) {
+			apply_event(&mut tasks, new_event);
+			apply_field_event(&mut fields, new_event);
+			apply_view_event(&mut views, new_event);
+		}
+		new_events.push(event);
+		new_events.extend(event_follow_ups.iter().cloned());
+		follow_up_events.extend(event_follow_ups);
+	}
+
+	let replaced: Vec<(EventId, Option<TaskEvent>)> = new_events
+		.into_iter()
+		.map(|event| (event.id, events.insert(event.id, event)))
+		.collect();
+	if let Err(error) = write_events(config, hashmap_to_sorted_vec(&events), encryption_key) {
+		for (id, previous) in replaced.into_iter().rev() {
+			match previous {
+				Some(previous) => events.insert(id, previous),
+				None => events.remove(&id),
+			};
+		}
+		return Err(error);
+	}
+	Ok(follow_up_events)
 }
 
-fn process_event_data(
+pub fn process_event_data(
 	encrypted_data: &[u8],
-	encryption_key: &State<EncryptionKey>,
+	encryption_key: &EncryptionKey,
 ) -> Result<Vec<TaskEvent>, TasksError> {
 	let tasks_json = decrypt(&encrypted_data, &encryption_key.0.lock().unwrap())?;
-	let tasks_data: TasksData = serde_json::from_str(&tasks_json)?;
+	let tasks_data = migrate(serde_json::from_str(&tasks_json)?)?;
 	Ok(tasks_data.events)
 }
 
-pub fn load_events(
-	config: &Config,
-	encryption_key: &State<EncryptionKey>,
-	event_store: &State<EventStore>,
+/// Merges the events from each readable file into the store, returning only the ones it did not already contain.
+pub fn merge_events_from_files(
+	paths: &[PathBuf],
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
 ) -> Result<Vec<TaskEvent>, TasksError> {
 	let mut all_events = event_store.events.lock().unwrap();
+	let mut added = Vec::new();
 
-	for tasks_path in get_tasks_paths(config) {
-		if let Ok(encrypted_data) = read_file_into_buffer(&tasks_path) {
+	for tasks_path in paths {
+		if let Ok(encrypted_data) = read_file_into_buffer(tasks_path) {
 			let file_events = process_event_data(&encrypted_data, encryption_key)?;
+			added.extend(merge_events(&mut all_events, file_events));
+		}
+	}
+
+	added.sort();
+	Ok(added)
+}
+
+/// Like `merge_events_from_files`, but skips files which can't be decrypted, eg while they are still syncing.
+/// Returns the events added along with the skipped paths.
+pub fn merge_readable_events_from_files(
+	paths: &[PathBuf],
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> (Vec<TaskEvent>, Vec<PathBuf>) {
+	let mut added = Vec::new();
+	let mut unreadable = Vec::new();
+	for path in paths {
+		match merge_events_from_files(slice::from_ref(path), encryption_key, event_store) {
+			Ok(path_added) => added.extend(path_added),
+			Err(_) => unreadable.push(path.clone()),
+		}
+	}
+	added.sort();
+	(added, unreadable)
+}
+
+/// Brings in whatever other instances have written since the replicas were last read, so that the next write
+/// doesn't overwrite it. The caller must hold the lock.
+fn merge_replicas(config: &Config, encryption_key: &EncryptionKey, event_store: &EventStore) {
+	let (added, _) =
+		merge_readable_events_from_files(&get_tasks_paths(config), encryption_key, event_store);
+	event_store.unannounced.lock().unwrap().extend(added);
+}
+
+/// Merges every readable replica and conflict copy without writing anything back, for callers which need
+/// all the events any device has saved. The caller must hold the lock.
+pub fn merge_all_replicas(
+	config: &Config,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let mut paths = get_tasks_paths(config);
+	paths.extend(get_tasks_conflict_copy_paths(config));
+	let (added, _) = merge_readable_events_from_files(&paths, encryption_key, event_store);
+	event_store.unannounced.lock().unwrap().extend(added);
+	Ok(hashmap_to_sorted_vec(&event_store.events.lock().unwrap()))
+}
+
+/// Replaces the events everywhere they are stored with those `rewrite` keeps. Replicas and conflict copies are
+/// merged in first and the copies removed, so that nothing dropped survives in a copy. Copies which can't be
+/// decrypted are left alone.
+pub fn rewrite_events(
+	config: &Config,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+	rewrite: impl FnOnce(Vec<TaskEvent>) -> Result<Vec<TaskEvent>, TasksError>,
+) -> Result<(), TasksError> {
+	let _lock = acquire_lock(&config.context)?;
+	merge_replicas(config, encryption_key, event_store);
+	let conflict_copy_paths = get_tasks_conflict_copy_paths(config);
+	let (added, unreadable) =
+		merge_readable_events_from_files(&conflict_copy_paths, encryption_key, event_store);
+
+	let mut events = event_store.events.lock().unwrap();
+	let kept_events = rewrite(hashmap_to_sorted_vec(&events))?;
+	write_events(config, kept_events.clone(), encryption_key)?;
+	*events = kept_events
+		.into_iter()
+		.map(|event| (event.id, event))
+		.collect();
+	let mut unannounced = event_store.unannounced.lock().unwrap();
+	unannounced.extend(added);
+	unannounced.retain(|event| events.contains_key(&event.id));
 
-			for event in file_events {
-				all_events.entry(event.id).or_insert(event);
+	for path in conflict_copy_paths {
+		if !unreadable.contains(&path) {
+			remove_file(path)?;
+		}
+	}
+	Ok(())
+}
+
+pub fn load_events(
+	config: &Config,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<Vec<TaskEvent>, TasksError> {
+	let _lock = acquire_lock(&config.context)?;
+	// A truncated or half-synced replica is skipped and shows up in the replica status instead,
+	// but if none can be read the key is probably wrong
+	let mut readable = false;
+	let mut first_error = None;
+	for path in get_tasks_paths(config).iter().filter(|path| path.exists()) {
+		match merge_events_from_files(slice::from_ref(path), encryption_key, event_store) {
+			Ok(_) => readable = true,
+			Err(error) => {
+				first_error.get_or_insert(error);
 			}
 		}
 	}
+	if let (false, Some(error)) = (readable, first_error) {
+		return Err(error);
+	}
 
-	let sorted_events = hashmap_to_sorted_vec(&all_events);
+	// Copies which can't be decrypted are left alone rather than blocking the load
+	let merged_conflict_copies: Vec<PathBuf> = get_tasks_conflict_copy_paths(config)
+		.into_iter()
+		.filter(|path| {
+			merge_events_from_files(slice::from_ref(path), encryption_key, event_store).is_ok()
+		})
+		.collect();
+
+	let sorted_events = hashmap_to_sorted_vec(&event_store.events.lock().unwrap());
+	if !merged_conflict_copies.is_empty() {
+		write_events(config, sorted_events.clone(), encryption_key)?;
+		for path in merged_conflict_copies {
+			remove_file(path)?;
+		}
+	}
 	Ok(sorted_events)
 }
 
@@ -140,24 +500,44 @@ pub fn change_password(
 	config: &Config,
 	current_password: &str,
 	new_password: &str,
-	encryption_key: &State<EncryptionKey>,
-	event_store: &State<EventStore>,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
 ) -> Result<(), TasksError> {
-	let current_salt = load_salt(config)?;
+	let lock = acquire_lock(&config.context)?;
+	let current_salt = read_salt(
+		&find_first_existing_file(&get_salt_paths(config))
+			.ok_or_else(|| TasksError::CryptoError("Vault has no salt".to_string()))?,
+	)?;
 	let mut key_to_check = [0; ENCRYPTION_KEY_SIZE];
 	derive_key(current_password, &current_salt, &mut key_to_check)?;
 	if key_to_check != *encryption_key.0.lock().unwrap() {
 		return Err(TasksError::CryptoError("Incorrect password".to_string()));
 	}
-	let events = load_events(config, encryption_key, event_store)?;
+	merge_replicas(config, encryption_key, event_store);
+	let events = hashmap_to_sorted_vec(&event_store.events.lock().unwrap());
+	let archive = load_archive(config, encryption_key)?;
+	let old_key = *encryption_key.0.lock().unwrap();
 
-	let new_salt = create_new_salt(config)?;
+	let mut new_salt: Salt = [0u8; SALT_SIZE];
+	generate_random_bytes(&mut new_salt);
+	let new_encryption_key = EncryptionKey::default();
 	derive_key(
 		new_password,
 		&new_salt,
-		&mut encryption_key.0.lock().unwrap(),
+		&mut new_encryption_key.0.lock().unwrap(),
 	)?;
-	save_events(config, events, &encryption_key)?;
+	let new_key = *new_encryption_key.0.lock().unwrap();
+
+	write_events(config, events, &new_encryption_key)?;
+	if get_archive_paths(config).iter().any(|path| path.exists()) {
+		lock.refresh()?;
+		write_archive(config, archive, &new_encryption_key)?;
+	}
+	reencrypt_attachments(&get_attachments_dirs(config), &old_key, &new_key, &lock)?;
+	lock.refresh()?;
+	// Written last, so the old salt keeps matching the files until every one has been re-encrypted
+	save_data_to_files(&new_salt, get_salt_paths(config))?;
+	*encryption_key.0.lock().unwrap() = new_key;
 
 	Ok(())
 }
@@ -165,31 +545,33 @@ pub fn change_password(
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::config::{SHUSHING_FACE_DIRNAME, TASKS_FILENAME};
+	use crate::config::{SALT_FILENAME, SHUSHING_FACE_DIRNAME, TASKS_FILENAME};
+	use crate::event::{EventId, TaskEventData};
+	use crate::task::Task;
 	use std::fs::{self, File};
-	use tempfile::tempdir;
+	use std::path::Path;
+	use tempfile::{tempdir, TempDir};
 
-	fn setup() -> (Config, PathBuf) {
+	/// Keeps every file the test touches, lock and config included, inside a temporary directory.
+	fn setup() -> (Config, TempDir) {
 		let tmp_dir = tempdir().unwrap();
-		let config: Config = Default::default();
-		(config, tmp_dir.into_path())
-	}
-
-	fn teardown(tmp_dir: PathBuf) {
-		fs::remove_dir_all(tmp_dir).unwrap();
+		let config = Config::new(&AppContext::new(tmp_dir.path()));
+		(config, tmp_dir)
 	}
 
 	#[test]
 	fn test_check_exists() {
 		let (config, tmp_dir) = setup();
-		let path = tmp_dir.join(SHUSHING_FACE_DIRNAME).join(TASKS_FILENAME);
+		assert!(!check_exists(&config).unwrap());
+
+		let path = tmp_dir
+			.path()
+			.join(SHUSHING_FACE_DIRNAME)
+			.join(TASKS_FILENAME);
 		fs::create_dir_all(path.parent().unwrap()).unwrap();
 		File::create(&path).unwrap();
 
-		let exists = check_exists(&config).unwrap();
-		assert!(exists);
-
-		teardown(tmp_dir);
+		assert!(check_exists(&config).unwrap());
 	}
 
 	#[test]
@@ -199,7 +581,366 @@ mod tests {
 		let loaded_salt = load_salt(&config).unwrap();
 
 		assert_eq!(salt, loaded_salt);
+		assert!(tmp_dir
+			.path()
+			.join(SHUSHING_FACE_DIRNAME)
+			.join(SALT_FILENAME)
+			.exists());
+	}
+
+	#[test]
+	fn test_save_and_load_config() {
+		let (config, tmp_dir) = setup();
+		let config = Config {
+			auto_lock_timeout: 5,
+			vault_id: 1,
+			..config
+		};
+		save_config(&config).unwrap();
+
+		let loaded_config = load_config(&config.context, 1);
+		assert_eq!(loaded_config.auto_lock_timeout, 5);
+		assert_eq!(loaded_config.vault_id, 1);
+		assert_eq!(loaded_config.context, config.context);
+		assert_eq!(load_config(&config.context, 2).auto_lock_timeout, 10);
+		assert!(get_config_path(&config.context, 1).starts_with(tmp_dir.path()));
+	}
+
+	#[test]
+	fn test_save_and_load_events() {
+		let (config, tmp_dir) = setup();
+		let encryption_key = EncryptionKey::default();
+		save_events(&config, vec![create_event(1)], &encryption_key).unwrap();
+
+		let loaded_events = load_events(&config, &encryption_key, &EventStore::new()).unwrap();
+
+		assert_eq!(loaded_events, vec![create_event(1)]);
+		assert!(!get_lock_path(&config.context).exists());
+		assert!(tmp_dir
+			.path()
+			.join(SHUSHING_FACE_DIRNAME)
+			.join(TASKS_FILENAME)
+			.exists());
+	}
+
+	fn save_events_under_salt(path: &Path, password: &str, salt: &Salt, events: Vec<TaskEvent>) {
+		let encryption_key = EncryptionKey::default();
+		derive_key(password, salt, &mut encryption_key.0.lock().unwrap()).unwrap();
+		let context = AppContext::new(path.parent().unwrap());
+		save_events_to_paths(&context, vec![path.to_path_buf()], events, &encryption_key).unwrap();
+	}
+
+	fn create_event(id: EventId) -> TaskEvent {
+		TaskEvent {
+			id,
+			data: TaskEventData::DeleteTask(id),
+		}
+	}
+
+	#[test]
+	fn test_resolve_divergent_salts() {
+		let tmp_dir = tempdir().unwrap();
+		let home_path = tmp_dir.path().join("home").join(TASKS_FILENAME);
+		let icloud_path = tmp_dir.path().join("icloud").join(TASKS_FILENAME);
+		let home_salt = [1u8; SALT_SIZE];
+		let icloud_salt = [2u8; SALT_SIZE];
+		save_events_under_salt(&home_path, "password", &home_salt, vec![create_event(1)]);
+		save_events_under_salt(
+			&icloud_path,
+			"password",
+			&icloud_salt,
+			vec![create_event(2)],
+		);
+
+		let (salt, mut events) = resolve_divergent_salts(
+			"password",
+			&[icloud_salt, home_salt],
+			&[home_path, icloud_path],
+		)
+		.unwrap();
+		events.sort();
+
+		assert_eq!(salt, home_salt);
+		assert_eq!(events, vec![create_event(1), create_event(2)]);
+	}
+
+	#[test]
+	fn test_resolve_divergent_salts_with_incorrect_password() {
+		let tmp_dir = tempdir().unwrap();
+		let home_path = tmp_dir.path().join("home").join(TASKS_FILENAME);
+		let home_salt = [1u8; SALT_SIZE];
+		save_events_under_salt(&home_path, "password", &home_salt, vec![create_event(1)]);
+
+		let result =
+			resolve_divergent_salts("incorrect", &[home_salt, [2u8; SALT_SIZE]], &[home_path]);
+
+		assert!(result.is_err());
+	}
+
+	#[test]
+	fn test_resolve_divergent_salts_without_tasks() {
+		let tmp_dir = tempdir().unwrap();
+		let home_path = tmp_dir.path().join("home").join(TASKS_FILENAME);
+		let home_salt = [1u8; SALT_SIZE];
+
+		let (salt, events) =
+			resolve_divergent_salts("password", &[home_salt, [2u8; SALT_SIZE]], &[home_path])
+				.unwrap();
+
+		assert_eq!(salt, home_salt);
+		assert!(events.is_empty());
+	}
+
+	#[test]
+	fn test_unlock_replicates_the_home_salt_without_tasks() {
+		let (config, _tmp_dir) = setup();
+		let config = Config {
+			icloud_enabled: true,
+			..config
+		};
+		let salt_paths = get_salt_paths(&config);
+		write_buffer_to_file(&salt_paths[0], &[1u8; SALT_SIZE]).unwrap();
+		write_buffer_to_file(&salt_paths[1], &[2u8; SALT_SIZE]).unwrap();
+
+		unlock(
+			&config,
+			"password",
+			&EncryptionKey::default(),
+			&EventStore::new(),
+		)
+		.unwrap();
+
+		assert_eq!(load_salts(&config).unwrap(), vec![[1u8; SALT_SIZE]]);
+	}
+
+	#[test]
+	fn test_sync_replicas_merges_a_newly_enabled_location() {
+		let (config, _tmp_dir) = setup();
+		let encryption_key = EncryptionKey::default();
+		let event_store = EventStore::new();
+		create_new_salt(&config).unwrap();
+		save_event(&config, create_event(1), &encryption_key, &event_store).unwrap();
+		let config = Config {
+			icloud_enabled: true,
+			..config
+		};
+		let context = AppContext::new(get_tasks_paths(&config)[1].parent().unwrap());
+		save_events_to_paths(
+			&context,
+			vec![get_tasks_paths(&config)[1].clone()],
+			vec![create_event(2)],
+			&encryption_key,
+		)
+		.unwrap();
+
+		sync_replicas(&config, None, &encryption_key, &event_store).unwrap();
+
+		for path in get_tasks_paths(&config) {
+			let events =
+				merge_events_from_files(&[path], &encryption_key, &EventStore::new()).unwrap();
+			assert_eq!(events, vec![create_event(1), create_event(2)]);
+		}
+		assert_eq!(
+			*event_store.unannounced.lock().unwrap(),
+			vec![create_event(2)]
+		);
+	}
+
+	#[test]
+	fn test_sync_replicas_reconciles_divergent_salts() {
+		let (config, _tmp_dir) = setup();
+		let home_salt = [1u8; SALT_SIZE];
+		let icloud_salt = [2u8; SALT_SIZE];
+		let encryption_key = EncryptionKey::default();
+		derive_key(
+			"password",
+			&home_salt,
+			&mut encryption_key.0.lock().unwrap(),
+		)
+		.unwrap();
+		save_salt(&config, &home_salt).unwrap();
+		let event_store = EventStore::new();
+		save_event(&config, create_event(1), &encryption_key, &event_store).unwrap();
+		let config = Config {
+			icloud_enabled: true,
+			..config
+		};
+		write_buffer_to_file(&get_salt_paths(&config)[1], &icloud_salt).unwrap();
+		save_events_under_salt(
+			&get_tasks_paths(&config)[1],
+			"password",
+			&icloud_salt,
+			vec![create_event(2)],
+		);
+
+		assert!(sync_replicas(&config, None, &encryption_key, &event_store).is_err());
+		sync_replicas(&config, Some("password"), &encryption_key, &event_store).unwrap();
+
+		assert_eq!(load_salts(&config).unwrap(), vec![home_salt]);
+		for path in get_tasks_paths(&config) {
+			let events =
+				merge_events_from_files(&[path], &encryption_key, &EventStore::new()).unwrap();
+			assert_eq!(events, vec![create_event(1), create_event(2)]);
+		}
+		assert_eq!(
+			*event_store.unannounced.lock().unwrap(),
+			vec![create_event(2)]
+		);
+	}
+
+	#[test]
+	fn test_change_password_reencrypts_events_and_archive() {
+		let (config, _tmp_dir) = setup();
+		let encryption_key = EncryptionKey::default();
+		let event_store = EventStore::new();
+		unlock(&config, "password", &encryption_key, &event_store).unwrap();
+		save_event(&config, create_event(1), &encryption_key, &event_store).unwrap();
+		crate::archive::save_archive(&config, vec![create_event(2)], &encryption_key).unwrap();
+		let old_salt = load_salts(&config).unwrap();
+
+		assert!(change_password(
+			&config,
+			"incorrect",
+			"new password",
+			&encryption_key,
+			&event_store
+		)
+		.is_err());
+		assert_eq!(load_salts(&config).unwrap(), old_salt);
+
+		change_password(
+			&config,
+			"password",
+			"new password",
+			&encryption_key,
+			&event_store,
+		)
+		.unwrap();
+
+		let salts = load_salts(&config).unwrap();
+		assert_ne!(salts, old_salt);
+		let new_encryption_key = EncryptionKey::default();
+		derive_key(
+			"new password",
+			&salts[0],
+			&mut new_encryption_key.0.lock().unwrap(),
+		)
+		.unwrap();
+		assert_eq!(
+			*encryption_key.0.lock().unwrap(),
+			*new_encryption_key.0.lock().unwrap()
+		);
+		assert_eq!(
+			load_events(&config, &new_encryption_key, &EventStore::new()).unwrap(),
+			vec![create_event(1)]
+		);
+		assert_eq!(
+			load_archive(&config, &new_encryption_key).unwrap(),
+			vec![create_event(2)]
+		);
+		assert!(!get_lock_path(&config.context).exists());
+	}
+
+	#[test]
+	fn test_load_events_skips_unreadable_replicas() {
+		let (config, _tmp_dir) = setup();
+		let config = Config {
+			icloud_enabled: true,
+			..config
+		};
+		let encryption_key = EncryptionKey::default();
+		save_events(&config, vec![create_event(1)], &encryption_key).unwrap();
+		let tasks_paths = get_tasks_paths(&config);
+		write_buffer_to_file(&tasks_paths[1], b"half synced").unwrap();
+
+		let events = load_events(&config, &encryption_key, &EventStore::new()).unwrap();
+		assert_eq!(events, vec![create_event(1)]);
+
+		write_buffer_to_file(&tasks_paths[0], b"truncated").unwrap();
+		assert!(load_events(&config, &encryption_key, &EventStore::new()).is_err());
+	}
+
+	#[test]
+	fn test_save_event_keeps_remote_events() {
+		let (config, _tmp_dir) = setup();
+		let encryption_key = EncryptionKey::default();
+		let create_task_event = |id: EventId| TaskEvent {
+			id,
+			data: TaskEventData::CreateTask(Task {
+				id,
+				..Default::default()
+			}),
+		};
+		let event_store = EventStore::new();
+		load_events(&config, &encryption_key, &event_store).unwrap();
+		// Written by another instance after this one loaded
+		save_events(&config, vec![create_task_event(1)], &encryption_key).unwrap();
+
+		save_event(&config, create_task_event(2), &encryption_key, &event_store).unwrap();
+
+		assert_eq!(
+			load_events(&config, &encryption_key, &EventStore::new()).unwrap(),
+			vec![create_task_event(1), create_task_event(2)]
+		);
+		assert_eq!(
+			*event_store.unannounced.lock().unwrap(),
+			vec![create_task_event(1)]
+		);
+	}
+
+	#[test]
+	fn test_merge_all_replicas_includes_conflict_copies() {
+		let (config, _tmp_dir) = setup();
+		let encryption_key = EncryptionKey::default();
+		let create_task_event = |id: EventId| TaskEvent {
+			id,
+			data: TaskEventData::CreateTask(Task {
+				id,
+				..Default::default()
+			}),
+		};
+		save_events(&config, vec![create_task_event(1)], &encryption_key).unwrap();
+		let tasks_path = get_tasks_paths(&config)[0].clone();
+		let conflict_copy_path = tasks_path.with_file_name(format!("{} (1)", TASKS_FILENAME));
+		fs::rename(&tasks_path, &conflict_copy_path).unwrap();
+		save_events(&config, vec![create_task_event(2)], &encryption_key).unwrap();
+
+		let event_store = EventStore::new();
+		let _lock = acquire_lock(&config.context).unwrap();
+		assert_eq!(
+			merge_all_replicas(&config, &encryption_key, &event_store).unwrap(),
+			vec![create_task_event(1), create_task_event(2)]
+		);
+		assert_eq!(event_store.unannounced.lock().unwrap().len(), 2);
+		assert!(conflict_copy_path.exists());
+	}
+
+	#[test]
+	fn test_save_events_batch_is_all_or_nothing() {
+		let (config, _tmp_dir) = setup();
+		let encryption_key = EncryptionKey::default();
+		let event_store = EventStore::new();
+		let batch = vec![
+			TaskEvent {
+				id: 1,
+				data: TaskEventData::CreateTask(Task {
+					id: 1,
+					..Default::default()
+				}),
+			},
+			TaskEvent {
+				id: 2,
+				data: TaskEventData::CreateTask(Task {
+					id: 2,
+					parent: Some(9),
+					..Default::default()
+				}),
+			},
+		];
+
+		let result = save_events_batch(&config, batch, &encryption_key, &event_store);
 
-		teardown(tmp_dir);
+		assert!(matches!(result, Err(TasksError::InvalidEventError(_))));
+		assert!(event_store.events.lock().unwrap().is_empty());
 	}
 }
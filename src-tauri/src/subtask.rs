@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+use crate::reducer::Tasks;
+use crate::task::{Task, TaskId};
+use crate::workflow::Status;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChecklistItem {
+	pub text: String,
+	pub done: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+	pub done: usize,
+	pub total: usize,
+}
+
+/// Whether giving `task_id` this parent would make it its own ancestor.
+pub fn would_create_cycle(tasks: &Tasks, task_id: TaskId, parent: Option<TaskId>) -> bool {
+	let mut ancestor = parent;
+	// Bounded in case the tasks already contain a cycle
+	for _ in 0..=tasks.len() {
+		match ancestor {
+			None => return false,
+			Some(ancestor_id) if ancestor_id == task_id => return true,
+			Some(ancestor_id) => ancestor = tasks.get(&ancestor_id).and_then(|task| task.parent),
+		}
+	}
+	true
+}
+
+pub fn get_subtasks(tasks: &Tasks, parent: TaskId) -> Vec<Task> {
+	tasks
+		.values()
+		.filter(|task| task.parent == Some(parent))
+		.cloned()
+		.collect()
+}
+
+/// Counts direct subtasks and checklist items together, leaving out cancelled subtasks.
+pub fn get_progress(tasks: &Tasks, task: &Task) -> Progress {
+	let subtasks: Vec<Task> = get_subtasks(tasks, task.id)
+		.into_iter()
+		.filter(|subtask| subtask.status != Status::Cancelled)
+		.collect();
+	Progress {
+		done: subtasks
+			.iter()
+			.filter(|subtask| subtask.status == Status::Done)
+			.count() + task.checklist.iter().filter(|item| item.done).count(),
+		total: subtasks.len() + task.checklist.len(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn create_task(id: TaskId, parent: Option<TaskId>, status: Status) -> Task {
+		Task {
+			id,
+			parent,
+			status,
+			..Default::default()
+		}
+	}
+
+	fn create_tasks() -> Tasks {
+		Tasks::from([
+			(1, create_task(1, None, Status::Todo)),
+			(2, create_task(2, Some(1), Status::Done)),
+			(3, create_task(3, Some(2), Status::Todo)),
+			(4, create_task(4, Some(1), Status::InProgress)),
+			(5, create_task(5, Some(1), Status::Cancelled)),
+		])
+	}
+
+	#[test]
+	fn test_would_create_cycle() {
+		let tasks = create_tasks();
+
+		assert!(would_create_cycle(&tasks, 1, Some(1)));
+		assert!(would_create_cycle(&tasks, 1, Some(3)));
+		assert!(!would_create_cycle(&tasks, 3, Some(4)));
+		assert!(!would_create_cycle(&tasks, 1, None));
+	}
+
+	#[test]
+	fn test_get_progress() {
+		let mut tasks = create_tasks();
+		tasks.get_mut(&1).unwrap().checklist = vec![
+			ChecklistItem {
+				text: "Book venue".to_string(),
+				done: true,
+			},
+			ChecklistItem {
+				text: "Send invites".to_string(),
+				done: false,
+			},
+		];
+
+		assert_eq!(
+			get_progress(&tasks, &tasks[&1]),
+			Progress { done: 2, total: 4 }
+		);
+		assert_eq!(
+			get_progress(&tasks, &tasks[&4]),
+			Progress { done: 0, total: 0 }
+		);
+	}
+}
@@ -1,14 +1,177 @@
-use chrono::NaiveDate;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
+use crate::attachment::Attachment;
+use crate::field::{FieldId, FieldValue};
+use crate::recurrence::Recurrence;
+use crate::subtask::ChecklistItem;
+use crate::workflow::{Status, StatusChange};
+
 pub type TaskId = u64;
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// A wall-clock time in a named time zone, so it stays at the same local time across DST changes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ZonedDateTime {
+	pub local: NaiveDateTime,
+	pub time_zone: Tz,
+}
+
+impl ZonedDateTime {
+	pub fn to_datetime(self) -> DateTime<Tz> {
+		match self.time_zone.from_local_datetime(&self.local) {
+			LocalResult::Single(datetime) => datetime,
+			LocalResult::Ambiguous(earliest, _) => earliest,
+			// Skipped by a DST transition, so use the same wall-clock time an hour later
+			LocalResult::None => self
+				.time_zone
+				.from_local_datetime(&(self.local + Duration::hours(1)))
+				.earliest()
+				.unwrap_or_else(|| self.time_zone.from_utc_datetime(&self.local)),
+		}
+	}
+}
+
+impl PartialOrd for ZonedDateTime {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for ZonedDateTime {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.to_datetime()
+			.cmp(&other.to_datetime())
+			.then_with(|| self.time_zone.name().cmp(other.time_zone.name()))
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(untagged)]
+pub enum Deadline {
+	/// Due at any time on the given day, serialized as `YYYY-MM-DD`
+	Date(NaiveDate),
+	Zoned(ZonedDateTime),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+	Low,
+	Normal,
+	High,
+	Urgent,
+}
+
+impl Default for Priority {
+	fn default() -> Self {
+		Priority::Normal
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "camelCase")]
 pub struct Task {
 	pub id: TaskId,
 	pub description: String,
-	pub deadline: NaiveDate,
+	/// Undated tasks have no deadline
+	#[serde(default)]
+	pub deadline: Option<Deadline>,
 	pub details: String,
-	pub completed: bool,
+	#[serde(default)]
+	pub status: Status,
+	/// Recorded by the reducer from event timestamps, so any value sent by the frontend is ignored
+	#[serde(default)]
+	pub status_history: Vec<StatusChange>,
+	#[serde(default)]
+	pub recurrence: Option<Recurrence>,
+	#[serde(default)]
+	pub tags: Vec<String>,
+	#[serde(default)]
+	pub project: Option<String>,
+	#[serde(default)]
+	pub priority: Priority,
+	#[serde(default)]
+	pub parent: Option<TaskId>,
+	#[serde(default)]
+	pub checklist: Vec<ChecklistItem>,
+	/// Prerequisites which must be closed before this task can start
+	#[serde(default)]
+	pub depends_on: Vec<TaskId>,
+	#[serde(default)]
+	pub attachments: Vec<Attachment>,
+	#[serde(default)]
+	pub reminders: Vec<DateTime<Utc>>,
+	/// Values of user-defined fields
+	#[serde(default)]
+	pub fields: BTreeMap<FieldId, FieldValue>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_deserialize_deadline() {
+		let date: Deadline = serde_json::from_value(json!("2026-11-01")).unwrap();
+		assert_eq!(date, Deadline::Date("2026-11-01".parse().unwrap()));
+
+		let zoned: Deadline = serde_json::from_value(json!({
+			"local": "2026-11-01T15:00:00",
+			"timeZone": "Europe/London",
+		}))
+		.unwrap();
+		assert_eq!(
+			zoned,
+			Deadline::Zoned(ZonedDateTime {
+				local: "2026-11-01T15:00:00".parse().unwrap(),
+				time_zone: chrono_tz::Europe::London,
+			})
+		);
+	}
+
+	#[test]
+	fn test_deserialize_task_without_deadline() {
+		let task: Task = serde_json::from_value(json!({
+			"id": 1,
+			"description": "Someday",
+			"deadline": null,
+			"details": "",
+			"status": "Todo",
+		}))
+		.unwrap();
+		assert_eq!(task.deadline, None);
+		assert!(task.tags.is_empty());
+		assert_eq!(task.project, None);
+		assert_eq!(task.priority, Priority::Normal);
+	}
+
+	#[test]
+	fn test_zoned_date_time_in_dst_gap() {
+		let zoned = ZonedDateTime {
+			local: "2026-03-29T01:30:00".parse().unwrap(),
+			time_zone: chrono_tz::Europe::London,
+		};
+		assert_eq!(
+			zoned.to_datetime().naive_local(),
+			"2026-03-29T02:30:00".parse().unwrap()
+		);
+	}
+
+	#[test]
+	fn test_compare_zoned_date_times_across_time_zones() {
+		let london = ZonedDateTime {
+			local: "2026-11-01T15:00:00".parse().unwrap(),
+			time_zone: chrono_tz::Europe::London,
+		};
+		let new_york = ZonedDateTime {
+			local: "2026-11-01T12:00:00".parse().unwrap(),
+			time_zone: chrono_tz::America::New_York,
+		};
+		assert!(london < new_york);
+	}
 }
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::event::{TaskEvent, TaskEventData};
+use crate::reducer::Tasks;
+use crate::task::TaskId;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerEvent {
+	pub task_id: TaskId,
+	pub at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeEntry {
+	pub task_id: TaskId,
+	pub start: DateTime<Utc>,
+	pub end: DateTime<Utc>,
+	#[serde(default)]
+	pub note: String,
+}
+
+impl TimeEntry {
+	pub fn seconds(&self) -> i64 {
+		(self.end - self.start).num_seconds().max(0)
+	}
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeReport {
+	pub total_seconds: i64,
+	pub by_task: BTreeMap<TaskId, i64>,
+	/// Time on tasks with several tags counts towards each of them
+	pub by_tag: BTreeMap<String, i64>,
+}
+
+/// Replays timer events in order. Only one timer runs at a time, so starting another stops the current one.
+fn replay_timers(events: &[TaskEvent]) -> (Vec<TimeEntry>, Option<TimerEvent>) {
+	let mut entries = Vec::new();
+	let mut running: Option<TimerEvent> = None;
+	let mut stop = |running: &mut Option<TimerEvent>, end: DateTime<Utc>| {
+		if let Some(timer) = running.take() {
+			entries.push(TimeEntry {
+				task_id: timer.task_id,
+				start: timer.at,
+				end: end.max(timer.at),
+				note: String::new(),
+			});
+		}
+	};
+
+	for event in events {
+		match &event.data {
+			TaskEventData::StartTimer(timer) => {
+				stop(&mut running, timer.at);
+				running = Some(*timer);
+			}
+			TaskEventData::StopTimer(timer)
+				if running.map(|running| running.task_id) == Some(timer.task_id) =>
+			{
+				stop(&mut running, timer.at);
+			}
+			_ => {}
+		}
+	}
+	(entries, running)
+}
+
+/// A timer is an event in the vault, so it keeps running across lock and unlock.
+pub fn get_running_timer(events: &[TaskEvent]) -> Option<TimerEvent> {
+	replay_timers(events).1
+}
+
+/// Manual entries along with those recorded by stopped timers.
+pub fn get_time_entries(events: &[TaskEvent]) -> Vec<TimeEntry> {
+	let (mut entries, _) = replay_timers(events);
+	entries.extend(events.iter().filter_map(|event| match &event.data {
+		TaskEventData::AddTimeEntry(entry) => Some(entry.clone()),
+		_ => None,
+	}));
+	entries.sort_by_key(|entry| entry.start);
+	entries
+}
+
+/// Start and end of a range of whole local days, for reports.
+pub fn get_local_date_range(from: NaiveDate, to: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+	let day_start = |date: NaiveDate| {
+		let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+		Local
+			.from_local_datetime(&midnight)
+			.earliest()
+			.map(|datetime| datetime.with_timezone(&Utc))
+			.unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+	};
+	(day_start(from), day_start(to + Duration::days(1)))
+}
+
+/// Sums time between `from` and `to`, clipping entries which overlap either end.
+/// A running timer counts up to `now`.
+pub fn get_time_report(
+	tasks: &Tasks,
+	events: &[TaskEvent],
+	from: DateTime<Utc>,
+	to: DateTime<Utc>,
+	now: DateTime<Utc>,
+) -> TimeReport {
+	let mut entries = get_time_entries(events);
+	if let Some(timer) = get_running_timer(events) {
+		entries.push(TimeEntry {
+			task_id: timer.task_id,
+			start: timer.at,
+			end: now.max(timer.at),
+			note: String::new(),
+		});
+	}
+
+	let mut report = TimeReport::default();
+	for entry in entries {
+		let clipped = TimeEntry {
+			start: entry.start.max(from),
+			end: entry.end.min(to),
+			..entry
+		};
+		let seconds = clipped.seconds();
+		if seconds == 0 {
+			continue;
+		}
+
+		report.total_seconds += seconds;
+		*report.by_task.entry(clipped.task_id).or_default() += seconds;
+		if let Some(task) = tasks.get(&clipped.task_id) {
+			for tag in &task.tags {
+				*report.by_tag.entry(tag.clone()).or_default() += seconds;
+			}
+		}
+	}
+	report
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::task::Task;
+
+	fn time(time: &str) -> DateTime<Utc> {
+		format!("2026-10-19T{}:00Z", time).parse().unwrap()
+	}
+
+	fn start(id: u64, task_id: TaskId, at: &str) -> TaskEvent {
+		TaskEvent {
+			id,
+			data: TaskEventData::StartTimer(TimerEvent {
+				task_id,
+				at: time(at),
+			}),
+		}
+	}
+
+	fn stop(id: u64, task_id: TaskId, at: &str) -> TaskEvent {
+		TaskEvent {
+			id,
+			data: TaskEventData::StopTimer(TimerEvent {
+				task_id,
+				at: time(at),
+			}),
+		}
+	}
+
+	fn entry(task_id: TaskId, start: &str, end: &str) -> TimeEntry {
+		TimeEntry {
+			task_id,
+			start: time(start),
+			end: time(end),
+			note: String::new(),
+		}
+	}
+
+	#[test]
+	fn test_get_time_entries() {
+		let events = vec![
+			start(1, 1, "09:00"),
+			stop(2, 1, "10:00"),
+			// Starting a second timer stops the first
+			start(3, 1, "11:00"),
+			start(4, 2, "11:30"),
+			// Stopping a timer which is not running does nothing
+			stop(5, 1, "12:00"),
+			TaskEvent {
+				id: 6,
+				data: TaskEventData::AddTimeEntry(entry(3, "08:00", "08:15")),
+			},
+		];
+
+		assert_eq!(
+			get_time_entries(&events),
+			vec![
+				entry(3, "08:00", "08:15"),
+				entry(1, "09:00", "10:00"),
+				entry(1, "11:00", "11:30"),
+			]
+		);
+		assert_eq!(
+			get_running_timer(&events),
+			Some(TimerEvent {
+				task_id: 2,
+				at: time("11:30"),
+			})
+		);
+	}
+
+	#[test]
+	fn test_get_time_report() {
+		let tasks = Tasks::from([
+			(
+				1,
+				Task {
+					id: 1,
+					tags: vec!["client-a".to_string(), "billable".to_string()],
+					..Default::default()
+				},
+			),
+			(
+				2,
+				Task {
+					id: 2,
+					tags: vec!["billable".to_string()],
+					..Default::default()
+				},
+			),
+		]);
+		let events = vec![
+			start(1, 1, "08:00"),
+			stop(2, 1, "10:00"),
+			TaskEvent {
+				id: 3,
+				data: TaskEventData::AddTimeEntry(entry(2, "13:00", "13:30")),
+			},
+			start(4, 2, "17:00"),
+		];
+
+		let report = get_time_report(&tasks, &events, time("09:00"), time("18:00"), time("17:15"));
+
+		assert_eq!(report.total_seconds, 105 * 60);
+		assert_eq!(report.by_task, BTreeMap::from([(1, 3600), (2, 45 * 60)]));
+		assert_eq!(
+			report.by_tag,
+			BTreeMap::from([
+				("billable".to_string(), 105 * 60),
+				("client-a".to_string(), 3600),
+			])
+		);
+	}
+}
@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::crypto::EncryptionKey;
+use crate::error::TasksError;
+use crate::event::{
+	get_event_time, get_next_event_id, hashmap_to_sorted_vec, split_task_events, EventStore,
+	TaskEvent, TaskEventData,
+};
+use crate::reducer::{apply_event, create_restored_task, Tasks};
+use crate::storage::{rewrite_events, save_events_batch};
+use crate::task::{Task, TaskId};
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedTask {
+	pub task: Task,
+	pub deleted_at: DateTime<Utc>,
+}
+
+/// Each deleted task as it was just before deletion, unless it has since been restored or purged.
+pub fn get_trash(events: &[TaskEvent]) -> BTreeMap<TaskId, TrashedTask> {
+	let mut tasks = Tasks::new();
+	let mut trash = BTreeMap::new();
+	for event in events {
+		match &event.data {
+			TaskEventData::DeleteTask(task_id) => {
+				if let Some(task) = tasks.get(task_id) {
+					trash.insert(
+						*task_id,
+						TrashedTask {
+							task: task.clone(),
+							deleted_at: get_event_time(event.id),
+						},
+					);
+				}
+			}
+			TaskEventData::CreateTask(task) => {
+				trash.remove(&task.id);
+			}
+			_ => {}
+		}
+		apply_event(&mut tasks, event);
+	}
+	trash
+}
+
+pub fn get_expired_task_ids(
+	trash: &BTreeMap<TaskId, TrashedTask>,
+	now: DateTime<Utc>,
+	retention_days: u32,
+) -> BTreeSet<TaskId> {
+	let cutoff = now - Duration::days(retention_days as i64);
+	trash
+		.values()
+		.filter(|trashed| trashed.deleted_at < cutoff)
+		.map(|trashed| trashed.task.id)
+		.collect()
+}
+
+/// Recreates the task with a new `CreateTask` event, which is returned.
+pub fn restore_task(
+	config: &Config,
+	task_id: TaskId,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<TaskEvent, TasksError> {
+	let (event_id, trashed) = {
+		let events = event_store.events.lock().unwrap();
+		let trashed = get_trash(&hashmap_to_sorted_vec(&events))
+			.remove(&task_id)
+			.ok_or_else(|| {
+				TasksError::InvalidEventError(format!("Task {} is not in the trash", task_id))
+			})?;
+		(get_next_event_id(&events), trashed)
+	};
+
+	let event = TaskEvent {
+		id: event_id,
+		data: TaskEventData::CreateTask(create_restored_task(&event_store.tasks(), &trashed.task)),
+	};
+	save_events_batch(config, vec![event.clone()], encryption_key, event_store)?;
+	Ok(event)
+}
+
+/// Drops every event holding the contents of tasks deleted longer ago than the retention period, from each
+/// replica and conflict copy. The event log is never compacted, so there are no snapshots to scrub as well.
+/// The deletions themselves are kept, so stale copies merged back in from other replicas stay deleted.
+pub fn purge_trash(
+	config: &Config,
+	now: DateTime<Utc>,
+	encryption_key: &EncryptionKey,
+	event_store: &EventStore,
+) -> Result<Vec<TaskId>, TasksError> {
+	let mut task_ids = BTreeSet::new();
+	rewrite_events(config, encryption_key, event_store, |events| {
+		task_ids = get_expired_task_ids(&get_trash(&events), now, config.trash_retention_days);
+		Ok(split_task_events(events, &task_ids).0)
+	})?;
+	Ok(task_ids.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::{SHUSHING_FACE_DIRNAME, TASKS_FILENAME};
+	use crate::context::AppContext;
+	use crate::storage::{merge_events_from_files, save_events};
+	use crate::util::get_tasks_paths;
+	use std::fs::copy;
+	use tempfile::tempdir;
+
+	fn create_task(id: TaskId, description: &str) -> Task {
+		Task {
+			id,
+			description: description.to_string(),
+			..Default::default()
+		}
+	}
+
+	fn create_events() -> Vec<TaskEvent> {
+		vec![
+			TaskEvent {
+				id: 1790000000000,
+				data: TaskEventData::CreateTask(create_task(1, "First")),
+			},
+			TaskEvent {
+				id: 1790000060000,
+				data: TaskEventData::CreateTask(create_task(2, "Second")),
+			},
+			TaskEvent {
+				id: 1790000120000,
+				data: TaskEventData::UpdateTask(create_task(1, "Renamed")),
+			},
+			TaskEvent {
+				id: 1790000180000,
+				data: TaskEventData::DeleteTask(1),
+			},
+			TaskEvent {
+				id: 1790000240000,
+				data: TaskEventData::DeleteTask(2),
+			},
+			// Restored
+			TaskEvent {
+				id: 1790000300000,
+				data: TaskEventData::CreateTask(create_task(2, "Second")),
+			},
+		]
+	}
+
+	#[test]
+	fn test_get_trash() {
+		let trash = get_trash(&create_events());
+
+		assert_eq!(trash.keys().collect::<Vec<_>>(), vec![&1]);
+		assert_eq!(trash[&1].task.description, "Renamed");
+		assert_eq!(trash[&1].deleted_at, get_event_time(1790000180000));
+	}
+
+	#[test]
+	fn test_get_expired_task_ids() {
+		let trash = get_trash(&create_events());
+		let deleted_at = get_event_time(1790000180000);
+
+		assert!(get_expired_task_ids(&trash, deleted_at + Duration::days(30), 30).is_empty());
+		assert_eq!(
+			get_expired_task_ids(&trash, deleted_at + Duration::days(31), 30),
+			BTreeSet::from([1])
+		);
+	}
+
+	#[test]
+	fn test_purge_trash_scrubs_replicas_and_conflict_copies() {
+		let dir = tempdir().unwrap();
+		let config = Config {
+			dropbox_enabled: true,
+			..Config::new(&AppContext::new(dir.path()))
+		};
+		let encryption_key = EncryptionKey::default();
+		let events = create_events();
+		save_events(&config, events[..4].to_vec(), &encryption_key).unwrap();
+		let conflict_copy_path = dir
+			.path()
+			.join(SHUSHING_FACE_DIRNAME)
+			.join(format!("{} (conflicted copy)", TASKS_FILENAME));
+		copy(&get_tasks_paths(&config)[0], &conflict_copy_path).unwrap();
+		// Written by another device since this one loaded
+		save_events(&config, events, &encryption_key).unwrap();
+
+		let event_store = EventStore::new();
+		let purged = purge_trash(
+			&config,
+			get_event_time(1790000180000) + Duration::days(31),
+			&encryption_key,
+			&event_store,
+		)
+		.unwrap();
+
+		assert_eq!(purged, vec![1]);
+		assert!(!conflict_copy_path.exists());
+		for path in get_tasks_paths(&config) {
+			let replica_store = EventStore::new();
+			merge_events_from_files(&[path], &encryption_key, &replica_store).unwrap();
+			let replica_events = hashmap_to_sorted_vec(&replica_store.events.lock().unwrap());
+			assert_eq!(
+				replica_events,
+				split_task_events(create_events(), &BTreeSet::from([1])).0
+			);
+		}
+		assert_eq!(event_store.events.lock().unwrap().len(), 4);
+	}
+
+	#[test]
+	fn test_purged_events_leave_nothing_in_the_trash() {
+		let (kept_events, _) = split_task_events(create_events(), &BTreeSet::from([1]));
+
+		assert!(get_trash(&kept_events).is_empty());
+		assert!(kept_events
+			.iter()
+			.any(|event| event.data == TaskEventData::DeleteTask(1)));
+	}
+}
@@ -1,29 +1,44 @@
-use std::path::PathBuf;
-
-use home::home_dir;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
 
 use crate::config::{
-	Config, CONFIG_FILENAME, DROPBOX_DIRNAME, ICLOUD_DIRNAME, SALT_FILENAME, SHUSHING_FACE_DIRNAME,
-	TASKS_FILENAME,
+	Config, ARCHIVE_FILENAME, ATTACHMENTS_DIRNAME, CONFIG_FILENAME, DROPBOX_DIRNAME,
+	FIRED_REMINDERS_FILENAME, ICLOUD_DIRNAME, LOCK_FILENAME, REGISTRY_FILENAME, SALT_FILENAME,
+	SHUSHING_FACE_DIRNAME, TASKS_FILENAME, VAULTS_DIRNAME,
 };
+use crate::context::AppContext;
+use crate::vault::{VaultId, DEFAULT_VAULT_ID};
 
-fn get_home_dir() -> PathBuf {
-	home_dir().expect("Failed to get home directory")
-}
-
-fn get_paths_for_file(config: &Config, file_name: &str) -> Vec<PathBuf> {
-	let home = get_home_dir();
-	let mut dirs = vec![home];
+pub fn get_vault_dirs(config: &Config) -> Vec<PathBuf> {
+	let context = &config.context;
+	let mut dirs = vec![context.root_dir.clone()];
 
 	if config.icloud_enabled {
-		dirs.push(get_home_dir().join(ICLOUD_DIRNAME));
+		dirs.push(context.home_dir.join(ICLOUD_DIRNAME));
 	}
 	if config.dropbox_enabled {
-		dirs.push(get_home_dir().join(DROPBOX_DIRNAME));
+		dirs.push(context.home_dir.join(DROPBOX_DIRNAME));
 	}
 
 	dirs.into_iter()
-		.map(|dir| dir.join(SHUSHING_FACE_DIRNAME).join(file_name))
+		.map(|dir| dir.join(get_vault_subdir(config.vault_id)))
+		.collect()
+}
+
+/// Relative to the root directory and each synced folder.
+pub fn get_vault_subdir(vault_id: VaultId) -> PathBuf {
+	let dir = PathBuf::from(SHUSHING_FACE_DIRNAME);
+	if vault_id == DEFAULT_VAULT_ID {
+		dir
+	} else {
+		dir.join(VAULTS_DIRNAME).join(vault_id.to_string())
+	}
+}
+
+fn get_paths_for_file(config: &Config, file_name: &str) -> Vec<PathBuf> {
+	get_vault_dirs(config)
+		.into_iter()
+		.map(|dir| dir.join(file_name))
 		.collect()
 }
 
@@ -31,25 +46,106 @@ pub fn get_salt_paths(config: &Config) -> Vec<PathBuf> {
 	get_paths_for_file(config, SALT_FILENAME)
 }
 
-pub fn get_config_path() -> PathBuf {
-	get_home_dir()
-		.join(SHUSHING_FACE_DIRNAME)
+pub fn get_config_path(context: &AppContext, vault_id: VaultId) -> PathBuf {
+	context
+		.root_dir
+		.join(get_vault_subdir(vault_id))
 		.join(CONFIG_FILENAME)
 }
 
+/// Kept in the local directory only, since each device shows its own notifications.
+pub fn get_fired_reminders_path(context: &AppContext, vault_id: VaultId) -> PathBuf {
+	context
+		.root_dir
+		.join(get_vault_subdir(vault_id))
+		.join(FIRED_REMINDERS_FILENAME)
+}
+
+pub fn get_registry_path(context: &AppContext) -> PathBuf {
+	context
+		.root_dir
+		.join(SHUSHING_FACE_DIRNAME)
+		.join(REGISTRY_FILENAME)
+}
+
+/// Only the local directory is locked, since lock files in synced folders would propagate to other devices.
+/// The one lock covers every vault.
+pub fn get_lock_path(context: &AppContext) -> PathBuf {
+	context
+		.root_dir
+		.join(SHUSHING_FACE_DIRNAME)
+		.join(LOCK_FILENAME)
+}
+
 pub fn get_tasks_paths(config: &Config) -> Vec<PathBuf> {
 	get_paths_for_file(config, TASKS_FILENAME)
 }
 
+/// Matches the names sync services give to copies they could not reconcile,
+/// eg `tasks (conflicted copy 2026-01-02)` from Dropbox or `tasks 2` from iCloud.
+pub fn is_conflict_copy(file_name: &str, original: &str) -> bool {
+	let suffix = match file_name.strip_prefix(original) {
+		Some(rest) => match rest.strip_prefix(' ') {
+			Some(suffix) => suffix,
+			None => return false,
+		},
+		None => return false,
+	};
+	let is_number = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+
+	match suffix
+		.strip_prefix('(')
+		.and_then(|inner| inner.strip_suffix(')'))
+	{
+		Some(inner) => inner.contains("conflicted copy") || is_number(inner),
+		None => is_number(suffix),
+	}
+}
+
+pub fn find_conflict_copies(dir: &Path, original: &str) -> Vec<PathBuf> {
+	let mut paths: Vec<PathBuf> = read_dir(dir)
+		.map(|entries| {
+			entries
+				.filter_map(|entry| entry.ok())
+				.filter(|entry| {
+					entry
+						.file_name()
+						.to_str()
+						.map_or(false, |file_name| is_conflict_copy(file_name, original))
+				})
+				.map(|entry| entry.path())
+				.collect()
+		})
+		.unwrap_or_default();
+	paths.sort();
+	paths
+}
+
+pub fn get_tasks_conflict_copy_paths(config: &Config) -> Vec<PathBuf> {
+	get_vault_dirs(config)
+		.iter()
+		.flat_map(|dir| find_conflict_copies(dir, TASKS_FILENAME))
+		.collect()
+}
+
 pub fn find_first_existing_file(paths: &[PathBuf]) -> Option<PathBuf> {
 	paths.iter().find(|path| path.exists()).cloned()
 }
 
-// pub fn to_hex_string(bytes: &[u8]) -> String {
-// 	bytes
-// 		.map(|byte| format!("{:02x}", byte))
-// 		.collect::<String>()
-// }
+pub fn get_attachments_dirs(config: &Config) -> Vec<PathBuf> {
+	get_paths_for_file(config, ATTACHMENTS_DIRNAME)
+}
+
+pub fn get_archive_paths(config: &Config) -> Vec<PathBuf> {
+	get_paths_for_file(config, ARCHIVE_FILENAME)
+}
+
+pub fn to_hex_string(bytes: &[u8]) -> String {
+	bytes
+		.iter()
+		.map(|byte| format!("{:02x}", byte))
+		.collect::<String>()
+}
 
 #[cfg(test)]
 mod tests {
@@ -63,10 +159,106 @@ mod tests {
 			auto_lock_timeout: 10,
 			icloud_enabled: false,
 			dropbox_enabled: false,
+			workflow: Default::default(),
+			archive_after_days: 30,
+			trash_retention_days: 30,
+			vault_id: DEFAULT_VAULT_ID,
+			context: AppContext::new(Path::new("/tmp/root")),
 		};
 
 		let paths = get_paths_for_file(&config, "test_file.txt");
-		assert!(!paths.is_empty(), "Paths should not be empty.");
+		assert_eq!(
+			paths,
+			vec![PathBuf::from("/tmp/root/.shushing-face/test_file.txt")]
+		);
+	}
+
+	#[test]
+	fn test_get_vault_dirs() {
+		let config = Config {
+			icloud_enabled: true,
+			vault_id: 1,
+			..Config::new(&AppContext {
+				root_dir: PathBuf::from("/media/usb"),
+				home_dir: PathBuf::from("/home/sam"),
+			})
+		};
+
+		assert_eq!(
+			get_vault_dirs(&config),
+			vec![
+				PathBuf::from("/media/usb/.shushing-face/vaults/1"),
+				PathBuf::from("/home/sam")
+					.join(ICLOUD_DIRNAME)
+					.join(".shushing-face/vaults/1"),
+			]
+		);
+		assert_eq!(
+			get_lock_path(&config.context),
+			PathBuf::from("/media/usb/.shushing-face/lock")
+		);
+	}
+
+	#[test]
+	fn test_get_vault_subdir() {
+		assert_eq!(
+			get_vault_subdir(DEFAULT_VAULT_ID),
+			PathBuf::from(SHUSHING_FACE_DIRNAME)
+		);
+		assert_eq!(
+			get_vault_subdir(1790000000000),
+			PathBuf::from(SHUSHING_FACE_DIRNAME)
+				.join(VAULTS_DIRNAME)
+				.join("1790000000000")
+		);
+	}
+
+	#[test]
+	fn test_is_conflict_copy() {
+		assert!(is_conflict_copy(
+			"tasks (conflicted copy 2026-01-02)",
+			TASKS_FILENAME
+		));
+		assert!(is_conflict_copy(
+			"tasks (Sam's conflicted copy 2026-01-02)",
+			TASKS_FILENAME
+		));
+		assert!(is_conflict_copy("tasks 2", TASKS_FILENAME));
+		assert!(is_conflict_copy("tasks (1)", TASKS_FILENAME));
+
+		assert!(!is_conflict_copy(TASKS_FILENAME, TASKS_FILENAME));
+		assert!(!is_conflict_copy("tasks2", TASKS_FILENAME));
+		assert!(!is_conflict_copy("tasks backup", TASKS_FILENAME));
+		assert!(!is_conflict_copy("tasks (backup)", TASKS_FILENAME));
+		assert!(!is_conflict_copy("salt 2", TASKS_FILENAME));
+	}
+
+	#[test]
+	fn test_find_conflict_copies() {
+		let dir = tempdir().unwrap();
+		for file_name in [
+			TASKS_FILENAME,
+			"tasks 3",
+			"tasks (conflicted copy)",
+			SALT_FILENAME,
+		] {
+			File::create(dir.path().join(file_name)).unwrap();
+		}
+
+		let conflict_copies = find_conflict_copies(dir.path(), TASKS_FILENAME);
+
+		assert_eq!(
+			conflict_copies,
+			vec![
+				dir.path().join("tasks (conflicted copy)"),
+				dir.path().join("tasks 3"),
+			]
+		);
+	}
+
+	#[test]
+	fn test_to_hex_string() {
+		assert_eq!(to_hex_string(&[0, 15, 16, 255]), "000f10ff");
 	}
 
 	#[test]
@@ -0,0 +1,279 @@
+use std::collections::BTreeSet;
+use std::fs::{read_dir, remove_dir_all};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{
+	Config, DROPBOX_DIRNAME, ICLOUD_DIRNAME, SALT_FILENAME, SHUSHING_FACE_DIRNAME, VAULTS_DIRNAME,
+};
+use crate::context::AppContext;
+use crate::error::TasksError;
+use crate::fs::read_file_into_buffer;
+use crate::storage::{load_config, save_config, save_data_to_files};
+use crate::util::{get_registry_path, get_vault_dirs, get_vault_subdir};
+
+pub type VaultId = u64;
+
+/// The vault which existed before there could be several, kept where it always was.
+pub const DEFAULT_VAULT_ID: VaultId = 0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Vault {
+	pub id: VaultId,
+	pub name: String,
+}
+
+/// Kept locally rather than synced, like the lock file, since each device chooses its own vaults.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VaultRegistry {
+	pub vaults: Vec<Vault>,
+	pub active: VaultId,
+}
+
+impl Default for VaultRegistry {
+	fn default() -> Self {
+		VaultRegistry {
+			vaults: vec![Vault {
+				id: DEFAULT_VAULT_ID,
+				name: "Personal".to_string(),
+			}],
+			active: DEFAULT_VAULT_ID,
+		}
+	}
+}
+
+fn vault_error(message: String) -> TasksError {
+	TasksError::VaultError(message)
+}
+
+impl VaultRegistry {
+	pub fn get(&self, vault_id: VaultId) -> Result<&Vault, TasksError> {
+		self.vaults
+			.iter()
+			.find(|vault| vault.id == vault_id)
+			.ok_or_else(|| vault_error(format!("Vault {} does not exist", vault_id)))
+	}
+
+	fn validate_name(&self, vault_id: VaultId, name: &str) -> Result<String, TasksError> {
+		let name = name.trim();
+		if name.is_empty() {
+			return Err(vault_error("Vault name cannot be empty".to_string()));
+		}
+		if self
+			.vaults
+			.iter()
+			.any(|vault| vault.id != vault_id && vault.name.eq_ignore_ascii_case(name))
+		{
+			return Err(vault_error(format!("Vault {} already exists", name)));
+		}
+		Ok(name.to_string())
+	}
+
+	pub fn create(&mut self, vault_id: VaultId, name: &str) -> Result<Vault, TasksError> {
+		let vault = Vault {
+			id: vault_id,
+			name: self.validate_name(vault_id, name)?,
+		};
+		if self.get(vault_id).is_ok() {
+			return Err(vault_error(format!("Vault {} already exists", vault_id)));
+		}
+		self.vaults.push(vault.clone());
+		Ok(vault)
+	}
+
+	pub fn rename(&mut self, vault_id: VaultId, name: &str) -> Result<(), TasksError> {
+		let name = self.validate_name(vault_id, name)?;
+		self.get(vault_id)?;
+		for vault in self.vaults.iter_mut().filter(|vault| vault.id == vault_id) {
+			vault.name = name.clone();
+		}
+		Ok(())
+	}
+
+	/// The default vault's directory holds the others, and the active vault may be unlocked.
+	pub fn remove(&mut self, vault_id: VaultId) -> Result<Vault, TasksError> {
+		let vault = self.get(vault_id)?.clone();
+		if vault_id == DEFAULT_VAULT_ID {
+			return Err(vault_error(
+				"The default vault cannot be deleted".to_string(),
+			));
+		}
+		if vault_id == self.active {
+			return Err(vault_error(
+				"Switch to another vault before deleting this one".to_string(),
+			));
+		}
+		self.vaults.retain(|vault| vault.id != vault_id);
+		Ok(vault)
+	}
+
+	pub fn activate(&mut self, vault_id: VaultId) -> Result<(), TasksError> {
+		self.get(vault_id)?;
+		self.active = vault_id;
+		Ok(())
+	}
+}
+
+pub fn load_registry(context: &AppContext) -> VaultRegistry {
+	read_file_into_buffer(&get_registry_path(context))
+		.and_then(|registry_json| {
+			serde_json::from_slice::<VaultRegistry>(&registry_json).map_err(TasksError::from)
+		})
+		.unwrap_or_default()
+}
+
+pub fn save_registry(context: &AppContext, registry: &VaultRegistry) -> Result<(), TasksError> {
+	let registry_data = serde_json::to_string(registry)?;
+	save_data_to_files(
+		&registry_data.into_bytes(),
+		vec![get_registry_path(context)],
+	)
+}
+
+/// The local directory and every synced folder, whether or not any vault syncs to it yet.
+fn get_locations(context: &AppContext) -> [PathBuf; 3] {
+	[
+		context.root_dir.clone(),
+		context.home_dir.join(ICLOUD_DIRNAME),
+		context.home_dir.join(DROPBOX_DIRNAME),
+	]
+}
+
+fn has_vault(location: &Path, vault_id: VaultId) -> bool {
+	location
+		.join(get_vault_subdir(vault_id))
+		.join(SALT_FILENAME)
+		.exists()
+}
+
+/// Vaults created on another device reach this one through a synced folder, but not its registry.
+pub fn find_unregistered_vaults(
+	context: &AppContext,
+	registry: &VaultRegistry,
+) -> BTreeSet<VaultId> {
+	let locations = get_locations(context);
+	locations
+		.iter()
+		.filter_map(|location| {
+			read_dir(location.join(SHUSHING_FACE_DIRNAME).join(VAULTS_DIRNAME)).ok()
+		})
+		.flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+		.filter_map(|entry| entry.file_name().to_str()?.parse::<VaultId>().ok())
+		.filter(|vault_id| registry.get(*vault_id).is_err())
+		.filter(|vault_id| {
+			locations
+				.iter()
+				.any(|location| has_vault(location, *vault_id))
+		})
+		.collect()
+}
+
+/// Registers a vault found in a synced folder, syncing it to every folder it was found in.
+pub fn open_vault(
+	context: &AppContext,
+	registry: &mut VaultRegistry,
+	vault_id: VaultId,
+	name: &str,
+) -> Result<Vault, TasksError> {
+	let [local, icloud, dropbox] =
+		get_locations(context).map(|location| has_vault(&location, vault_id));
+	if !(local || icloud || dropbox) {
+		return Err(vault_error(format!("Vault {} was not found", vault_id)));
+	}
+	let vault = registry.create(vault_id, name)?;
+	save_config(&Config {
+		icloud_enabled: icloud,
+		dropbox_enabled: dropbox,
+		..load_config(context, vault_id)
+	})?;
+	save_registry(context, registry)?;
+	Ok(vault)
+}
+
+/// Removes the vault from the registry, then its files from every location its config syncs to.
+pub fn delete_vault(
+	context: &AppContext,
+	registry: &mut VaultRegistry,
+	vault_id: VaultId,
+) -> Result<(), TasksError> {
+	let config = load_config(context, vault_id);
+	registry.remove(vault_id)?;
+	save_registry(context, registry)?;
+	for dir in get_vault_dirs(&config) {
+		if dir.exists() {
+			remove_dir_all(dir)?;
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::util::get_config_path;
+	use std::fs::{create_dir_all, File};
+	use tempfile::tempdir;
+
+	#[test]
+	fn test_create_and_rename() {
+		let mut registry = VaultRegistry::default();
+
+		let work = registry.create(1, " Work ").unwrap();
+		assert_eq!(work.name, "Work");
+		assert!(registry.create(2, "work").is_err());
+		assert!(registry.create(2, "  ").is_err());
+		assert!(registry.create(1, "Side project").is_err());
+
+		assert!(registry.rename(1, "Personal").is_err());
+		registry.rename(1, "Job").unwrap();
+		registry.rename(1, "job").unwrap();
+		assert_eq!(registry.get(1).unwrap().name, "job");
+		assert!(registry.rename(9, "Other").is_err());
+	}
+
+	#[test]
+	fn test_activate_and_remove() {
+		let mut registry = VaultRegistry::default();
+		registry.create(1, "Work").unwrap();
+
+		registry.activate(1).unwrap();
+		assert_eq!(registry.active, 1);
+		assert!(registry.activate(9).is_err());
+
+		assert!(registry.remove(1).is_err());
+		assert!(registry.remove(DEFAULT_VAULT_ID).is_err());
+		registry.activate(DEFAULT_VAULT_ID).unwrap();
+		assert_eq!(registry.remove(1).unwrap().name, "Work");
+		assert_eq!(registry.vaults.len(), 1);
+	}
+
+	#[test]
+	fn test_open_vault_from_synced_folder() {
+		let dir = tempdir().unwrap();
+		let context = AppContext::new(dir.path());
+		let mut registry = VaultRegistry::default();
+		let synced_dir = dir.path().join(DROPBOX_DIRNAME).join(get_vault_subdir(7));
+		create_dir_all(&synced_dir).unwrap();
+		File::create(synced_dir.join(SALT_FILENAME)).unwrap();
+		// Left behind without a salt, eg by a deletion that hasn't fully synced
+		create_dir_all(dir.path().join(ICLOUD_DIRNAME).join(get_vault_subdir(8))).unwrap();
+
+		assert_eq!(
+			find_unregistered_vaults(&context, &registry),
+			BTreeSet::from([7])
+		);
+		assert!(open_vault(&context, &mut registry, 8, "Work").is_err());
+
+		assert_eq!(
+			open_vault(&context, &mut registry, 7, "Work").unwrap().name,
+			"Work"
+		);
+		assert_eq!(load_registry(&context), registry);
+		assert!(find_unregistered_vaults(&context, &registry).is_empty());
+		assert!(get_config_path(&context, 7).exists());
+		let config = load_config(&context, 7);
+		assert!(config.dropbox_enabled);
+		assert!(!config.icloud_enabled);
+	}
+}
@@ -0,0 +1,312 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::due::get_due_date;
+use crate::error::TasksError;
+use crate::event::{TaskEvent, TaskEventData};
+use crate::field::{FieldId, Fields};
+use crate::filter::{compare_field_values, filter_tasks, parse_filter};
+use crate::reducer::Tasks;
+use crate::task::Task;
+
+pub type ViewId = u64;
+
+pub type Views = BTreeMap<ViewId, View>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortField {
+	Deadline,
+	Priority,
+	Status,
+	Description,
+	/// Task ids are creation timestamps
+	Created,
+	/// A user-defined field
+	Field(FieldId),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SortKey {
+	pub field: SortField,
+	#[serde(default)]
+	pub descending: bool,
+}
+
+/// A named filter and sort order, stored as events so that it is encrypted and synced with the tasks.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct View {
+	pub id: ViewId,
+	pub name: String,
+	/// In the syntax of `parse_filter`
+	pub query: String,
+	#[serde(default)]
+	pub sort: Vec<SortKey>,
+}
+
+pub fn validate_view(view: &View, fields: &Fields) -> Result<(), TasksError> {
+	if view.name.trim().is_empty() {
+		return Err(TasksError::InvalidEventError(
+			"View name cannot be empty".to_string(),
+		));
+	}
+	parse_filter(&view.query, fields)?;
+	Ok(())
+}
+
+/// Queries refer to fields by name, so a field can't be renamed, retyped or deleted while a view's
+/// query would then fail to parse. Nor can a field be deleted while a view sorts by it.
+pub fn validate_views_with_fields(views: &Views, fields: &Fields) -> Result<(), TasksError> {
+	let is_deleted_field = |key: &SortKey| matches!(key.field, SortField::Field(field_id) if !fields.contains_key(&field_id));
+	match views.values().find(|view| {
+		parse_filter(&view.query, fields).is_err() || view.sort.iter().any(is_deleted_field)
+	}) {
+		Some(view) => Err(TasksError::InvalidEventError(format!(
+			"View {} uses the field, so it would stop working",
+			view.name
+		))),
+		None => Ok(()),
+	}
+}
+
+pub fn apply_view_event(views: &mut Views, event: &TaskEvent) {
+	match &event.data {
+		TaskEventData::SaveView(view) => {
+			views.insert(view.id, view.clone());
+		}
+		TaskEventData::DeleteView(view_id) => {
+			views.remove(view_id);
+		}
+		_ => {}
+	}
+}
+
+pub fn materialize_views(events: &[TaskEvent]) -> Views {
+	let mut views = Views::new();
+	for event in events {
+		apply_view_event(&mut views, event);
+	}
+	views
+}
+
+fn compare_by<Z: TimeZone>(field: SortField, a: &Task, b: &Task, now: &DateTime<Z>) -> Ordering {
+	match field {
+		// Undated tasks last
+		SortField::Deadline => match (&a.deadline, &b.deadline) {
+			(Some(a), Some(b)) => get_due_date(a, now)
+				.cmp(&get_due_date(b, now))
+				.then_with(|| a.cmp(b)),
+			(a, b) => a.is_none().cmp(&b.is_none()),
+		},
+		SortField::Priority => a.priority.cmp(&b.priority),
+		SortField::Status => a.status.cmp(&b.status),
+		SortField::Description => a
+			.description
+			.to_lowercase()
+			.cmp(&b.description.to_lowercase()),
+		SortField::Created => a.id.cmp(&b.id),
+		// Tasks without a value last
+		SortField::Field(field_id) => match (a.fields.get(&field_id), b.fields.get(&field_id)) {
+			(Some(a), Some(b)) => compare_field_values(a, b),
+			(a, b) => a.is_none().cmp(&b.is_none()),
+		},
+	}
+}
+
+/// Sorts by each key in turn, falling back to creation order.
+pub fn sort_tasks<Z: TimeZone>(tasks: &mut [Task], sort: &[SortKey], now: &DateTime<Z>) {
+	tasks.sort_by(|a, b| {
+		sort.iter()
+			.map(|key| {
+				let ordering = compare_by(key.field, a, b, now);
+				if key.descending {
+					ordering.reverse()
+				} else {
+					ordering
+				}
+			})
+			.find(|ordering| *ordering != Ordering::Equal)
+			.unwrap_or_else(|| a.id.cmp(&b.id))
+	});
+}
+
+pub fn evaluate_view<Z: TimeZone>(
+	view: &View,
+	tasks: &Tasks,
+	fields: &Fields,
+	now: &DateTime<Z>,
+) -> Result<Vec<Task>, TasksError> {
+	let mut matching = filter_tasks(&parse_filter(&view.query, fields)?, tasks, now);
+	sort_tasks(&mut matching, &view.sort, now);
+	Ok(matching)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::field::{FieldDefinition, FieldType, FieldValue, Number};
+	use crate::task::{Deadline, Priority, TaskId};
+	use chrono::Utc;
+
+	fn get_now() -> DateTime<Utc> {
+		"2026-10-21T12:00:00Z".parse().unwrap()
+	}
+
+	fn create_view(id: ViewId, name: &str, query: &str, sort: Vec<SortKey>) -> View {
+		View {
+			id,
+			name: name.to_string(),
+			query: query.to_string(),
+			sort,
+		}
+	}
+
+	#[test]
+	fn test_materialize_views() {
+		let this_week = create_view(1, "This week", "due:<=2026-10-25", Vec::new());
+		let events = vec![
+			TaskEvent {
+				id: 1,
+				data: TaskEventData::SaveView(create_view(1, "Week", "", Vec::new())),
+			},
+			TaskEvent {
+				id: 2,
+				data: TaskEventData::SaveView(create_view(2, "Overdue", "overdue", Vec::new())),
+			},
+			TaskEvent {
+				id: 3,
+				data: TaskEventData::SaveView(this_week.clone()),
+			},
+			TaskEvent {
+				id: 4,
+				data: TaskEventData::DeleteView(2),
+			},
+		];
+
+		assert_eq!(materialize_views(&events), BTreeMap::from([(1, this_week)]));
+	}
+
+	#[test]
+	fn test_validate_view() {
+		assert!(validate_view(
+			&create_view(1, "Work", "tag:work", Vec::new()),
+			&Fields::new()
+		)
+		.is_ok());
+		assert!(
+			validate_view(&create_view(1, " ", "tag:work", Vec::new()), &Fields::new()).is_err()
+		);
+		assert!(validate_view(
+			&create_view(1, "Work", "due:soon", Vec::new()),
+			&Fields::new()
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn test_validate_views_with_fields() {
+		let estimate = FieldDefinition {
+			id: 1,
+			name: "estimate".to_string(),
+			field_type: FieldType::Number,
+		};
+		let fields = Fields::from([(1, estimate.clone())]);
+		let filtering = Views::from([(1, create_view(1, "Big", "estimate:>3", Vec::new()))]);
+		let sorting = Views::from([(
+			1,
+			create_view(
+				1,
+				"Sized",
+				"",
+				vec![SortKey {
+					field: SortField::Field(1),
+					descending: false,
+				}],
+			),
+		)]);
+		let renamed = Fields::from([(
+			1,
+			FieldDefinition {
+				name: "effort".to_string(),
+				..estimate
+			},
+		)]);
+
+		assert!(validate_views_with_fields(&filtering, &fields).is_ok());
+		assert!(validate_views_with_fields(&filtering, &renamed).is_err());
+		assert!(validate_views_with_fields(&filtering, &Fields::new()).is_err());
+		assert!(validate_views_with_fields(&sorting, &renamed).is_ok());
+		assert!(validate_views_with_fields(&sorting, &Fields::new()).is_err());
+	}
+
+	#[test]
+	fn test_evaluate_view() {
+		let create_task = |id: TaskId, deadline: Option<&str>, priority: Priority| Task {
+			id,
+			deadline: deadline.map(|date| Deadline::Date(date.parse().unwrap())),
+			priority,
+			tags: vec!["work".to_string()],
+			..Default::default()
+		};
+		let tasks = Tasks::from([
+			(1, create_task(1, None, Priority::Urgent)),
+			(2, create_task(2, Some("2026-10-24"), Priority::Low)),
+			(3, create_task(3, Some("2026-10-22"), Priority::Low)),
+			(4, create_task(4, Some("2026-10-22"), Priority::High)),
+		]);
+		let view = create_view(
+			1,
+			"Work",
+			"tag:work",
+			vec![
+				SortKey {
+					field: SortField::Deadline,
+					descending: false,
+				},
+				SortKey {
+					field: SortField::Priority,
+					descending: true,
+				},
+			],
+		);
+
+		let ids: Vec<TaskId> = evaluate_view(&view, &tasks, &Fields::new(), &get_now())
+			.unwrap()
+			.iter()
+			.map(|task| task.id)
+			.collect();
+		assert_eq!(ids, vec![4, 3, 2, 1]);
+	}
+
+	#[test]
+	fn test_sort_tasks_by_field() {
+		let with_estimate = |id: TaskId, estimate: Option<f64>| Task {
+			id,
+			fields: estimate
+				.map(|estimate| BTreeMap::from([(1, FieldValue::Number(Number(estimate)))]))
+				.unwrap_or_default(),
+			..Default::default()
+		};
+		let mut tasks = vec![
+			with_estimate(1, None),
+			with_estimate(2, Some(3.0)),
+			with_estimate(3, Some(0.5)),
+		];
+
+		sort_tasks(
+			&mut tasks,
+			&[SortKey {
+				field: SortField::Field(1),
+				descending: false,
+			}],
+			&get_now(),
+		);
+
+		assert_eq!(
+			tasks.iter().map(|task| task.id).collect::<Vec<_>>(),
+			vec![3, 2, 1]
+		);
+	}
+}
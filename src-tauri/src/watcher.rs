@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use tauri::{AppHandle, Manager};
+
+use crate::config::AppConfig;
+use crate::crypto::EncryptionKey;
+use crate::event::EventStore;
+use crate::search::SearchStore;
+use crate::storage::merge_readable_events_from_files;
+use crate::util::{get_tasks_conflict_copy_paths, get_tasks_paths};
+
+pub const VAULT_CHANGED_EVENT: &str = "vault-changed";
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Tracks modification times so that only replicas touched since the last poll are reread.
+#[derive(Default)]
+pub struct ReplicaWatcher {
+	modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl ReplicaWatcher {
+	pub fn new() -> Self {
+		Default::default()
+	}
+
+	pub fn poll(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+		let mut changed = Vec::new();
+		for path in paths {
+			match path.metadata().and_then(|metadata| metadata.modified()) {
+				Ok(modified) => {
+					if self.modified.insert(path.clone(), modified) != Some(modified) {
+						changed.push(path.clone());
+					}
+				}
+				Err(_) => {
+					self.modified.remove(path);
+				}
+			}
+		}
+		changed
+	}
+
+	/// Makes the next poll report these paths again, eg if they were read mid-sync.
+	pub fn forget(&mut self, paths: &[PathBuf]) {
+		for path in paths {
+			self.modified.remove(path);
+		}
+	}
+
+	pub fn reset(&mut self) {
+		self.modified.clear();
+	}
+}
+
+pub fn watch_replicas(app_handle: AppHandle) {
+	thread::spawn(move || {
+		let mut watcher = ReplicaWatcher::new();
+		loop {
+			thread::sleep(WATCH_INTERVAL);
+
+			let encryption_key = app_handle.state::<EncryptionKey>();
+			if encryption_key.is_locked() {
+				watcher.reset();
+				continue;
+			}
+
+			let tasks_paths = {
+				let app_config = app_handle.state::<AppConfig>();
+				let config = app_config.config.lock().unwrap();
+				let mut paths = get_tasks_paths(&config);
+				paths.extend(get_tasks_conflict_copy_paths(&config));
+				paths
+			};
+			let changed = watcher.poll(&tasks_paths);
+			let event_store = app_handle.state::<EventStore>();
+			let (mut added, unreadable) =
+				merge_readable_events_from_files(&changed, &encryption_key, &event_store);
+			// Probably read mid-sync, so try again next time
+			watcher.forget(&unreadable);
+			added.append(&mut event_store.unannounced.lock().unwrap());
+			if !added.is_empty() {
+				added.sort();
+				app_handle
+					.state::<SearchStore>()
+					.index
+					.lock()
+					.unwrap()
+					.apply_events(&event_store.tasks(), &added);
+				let _ = app_handle.emit_all(VAULT_CHANGED_EVENT, added);
+			}
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use filetime::{set_file_mtime, FileTime};
+	use std::fs::{self, File};
+	use tempfile::tempdir;
+
+	#[test]
+	fn test_poll_reports_new_and_modified_files() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("tasks");
+		let paths = vec![path.clone()];
+		let mut watcher = ReplicaWatcher::new();
+
+		assert!(watcher.poll(&paths).is_empty());
+
+		File::create(&path).unwrap();
+		assert_eq!(watcher.poll(&paths), paths);
+		assert!(watcher.poll(&paths).is_empty());
+
+		let earlier = SystemTime::now() - Duration::from_secs(60);
+		set_file_mtime(&path, FileTime::from_system_time(earlier)).unwrap();
+		assert_eq!(watcher.poll(&paths), paths);
+	}
+
+	#[test]
+	fn test_forget_and_reset() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("tasks");
+		File::create(&path).unwrap();
+		let paths = vec![path.clone()];
+		let mut watcher = ReplicaWatcher::new();
+
+		watcher.poll(&paths);
+		watcher.forget(&paths);
+		assert_eq!(watcher.poll(&paths), paths);
+
+		watcher.reset();
+		assert_eq!(watcher.poll(&paths), paths);
+
+		fs::remove_file(&path).unwrap();
+		assert!(watcher.poll(&paths).is_empty());
+	}
+}
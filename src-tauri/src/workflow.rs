@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::task::Task;
+
+/// A fixed set, since closing, cycle time and recurrence depend on what each status means.
+/// Workflows choose which of them to offer rather than defining their own.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Status {
+	Todo,
+	InProgress,
+	Waiting,
+	Done,
+	Cancelled,
+}
+
+impl Default for Status {
+	fn default() -> Self {
+		Status::Todo
+	}
+}
+
+impl Status {
+	/// Done or cancelled, so no longer needs doing.
+	pub fn is_closed(self) -> bool {
+		matches!(self, Status::Done | Status::Cancelled)
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StatusChange {
+	pub status: Status,
+	pub at: DateTime<Utc>,
+}
+
+/// The subset of statuses offered to the user and the transitions allowed between them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Workflow {
+	pub states: Vec<Status>,
+	pub transitions: BTreeMap<Status, Vec<Status>>,
+}
+
+impl Default for Workflow {
+	fn default() -> Self {
+		use Status::*;
+		Workflow {
+			states: vec![Todo, InProgress, Waiting, Done, Cancelled],
+			transitions: BTreeMap::from([
+				(Todo, vec![InProgress, Waiting, Done, Cancelled]),
+				(InProgress, vec![Todo, Waiting, Done, Cancelled]),
+				(Waiting, vec![Todo, InProgress, Done, Cancelled]),
+				(Done, vec![Todo]),
+				(Cancelled, vec![Todo]),
+			]),
+		}
+	}
+}
+
+impl Workflow {
+	pub fn can_transition(&self, from: Status, to: Status) -> bool {
+		from == to
+			|| (self.states.contains(&to)
+				&& self
+					.transitions
+					.get(&from)
+					.map_or(false, |targets| targets.contains(&to)))
+	}
+}
+
+/// Time from first starting work on a task until it was last done.
+pub fn get_cycle_time(task: &Task) -> Option<Duration> {
+	if task.status != Status::Done {
+		return None;
+	}
+	let started = task
+		.status_history
+		.iter()
+		.find(|change| change.status == Status::InProgress)?;
+	let done = task
+		.status_history
+		.iter()
+		.rev()
+		.find(|change| change.status == Status::Done)?;
+	Some(done.at - started.at).filter(|duration| *duration >= Duration::zero())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn change(status: Status, at: &str) -> StatusChange {
+		StatusChange {
+			status,
+			at: at.parse().unwrap(),
+		}
+	}
+
+	#[test]
+	fn test_can_transition() {
+		let workflow = Workflow::default();
+		assert!(workflow.can_transition(Status::Todo, Status::InProgress));
+		assert!(workflow.can_transition(Status::Done, Status::Done));
+		assert!(!workflow.can_transition(Status::Done, Status::InProgress));
+
+		let without_waiting = Workflow {
+			states: vec![Status::Todo, Status::InProgress, Status::Done],
+			..Workflow::default()
+		};
+		assert!(!without_waiting.can_transition(Status::Todo, Status::Waiting));
+	}
+
+	#[test]
+	fn test_deserialize_workflow() {
+		let workflow: Workflow = serde_json::from_str(
+			r#"{ "states": ["Todo", "Done"], "transitions": { "Todo": ["Done"] } }"#,
+		)
+		.unwrap();
+		assert!(workflow.can_transition(Status::Todo, Status::Done));
+		assert!(!workflow.can_transition(Status::Done, Status::Todo));
+	}
+
+	#[test]
+	fn test_get_cycle_time() {
+		let mut task = Task {
+			status: Status::Done,
+			status_history: vec![
+				change(Status::Todo, "2026-10-01T09:00:00Z"),
+				change(Status::InProgress, "2026-10-02T09:00:00Z"),
+				change(Status::Waiting, "2026-10-03T09:00:00Z"),
+				change(Status::InProgress, "2026-10-04T09:00:00Z"),
+				change(Status::Done, "2026-10-05T21:00:00Z"),
+			],
+			..Default::default()
+		};
+		assert_eq!(get_cycle_time(&task), Some(Duration::hours(84)));
+
+		task.status = Status::Todo;
+		assert_eq!(get_cycle_time(&task), None);
+	}
+}
@@ -1,5 +1,15 @@
+import type { Status } from "./task";
+
+export interface Workflow {
+	states: Status[];
+	transitions: Partial<Record<Status, Status[]>>;
+}
+
 export interface Config {
 	autoLockTimeout: number;
 	icloudEnabled: boolean;
 	dropboxEnabled: boolean;
+	workflow: Workflow;
+	archiveAfterDays: number;
+	trashRetentionDays: number;
 }
@@ -2,6 +2,7 @@ export enum DateGroup {
 	Past = "Past",
 	Today = "Today",
 	Tomorrow = "Tomorrow",
+	Undated = "Undated",
 }
 
 export const toIsoDateString = (date: Date): string =>
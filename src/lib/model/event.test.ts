@@ -9,15 +9,17 @@ import {
 	isCreateTaskEvent,
 	isUpdateTaskEvent,
 	isDeleteTaskEvent,
+	isTaskEvent,
+	type FormattedTaskEvent,
 } from "./event";
-import type { Task } from "./task";
+import { type Task, Status } from "./task";
 
 const mockTask: Task = {
 	id: 1,
 	description: "Test Task",
 	deadline: "2023-01-01",
 	details: "Test Details",
-	completed: false,
+	status: Status.Todo,
 };
 
 const mockTasks: Task[] = [mockTask];
@@ -59,6 +61,17 @@ describe("applyEvent", () => {
 		const updatedTasks = applyEvent([...mockTasks], event);
 		expect(updatedTasks).not.toContainEqual(mockTask);
 	});
+
+	it("applies ArchiveTask event correctly", () => {
+		const event: TaskEvent = {
+			type: TaskEventType.ArchiveTask,
+			id: 1,
+			taskId: mockTask.id,
+		};
+
+		const updatedTasks = applyEvent([...mockTasks], event);
+		expect(updatedTasks).not.toContainEqual(mockTask);
+	});
 });
 
 describe("applyEvents", () => {
@@ -79,6 +92,31 @@ describe("applyEvents", () => {
 		expect(updatedTasks).toContainEqual({ ...newTask, description: "Updated" });
 		expect(updatedTasks).not.toContainEqual(mockTask);
 	});
+
+	it("sorts by deadline with undated tasks last", () => {
+		const events: TaskEvent[] = [
+			{
+				type: TaskEventType.CreateTask,
+				id: 2,
+				task: { ...mockTask, id: 2, deadline: null },
+			},
+			{
+				type: TaskEventType.CreateTask,
+				id: 3,
+				task: {
+					...mockTask,
+					id: 3,
+					deadline: {
+						local: "2022-12-31T09:00:00",
+						timeZone: "Europe/London",
+					},
+				},
+			},
+		];
+
+		const updatedTasks = applyEvents([...mockTasks], events);
+		expect(updatedTasks.map((task) => task.id)).toEqual([3, 1, 2]);
+	});
 });
 
 describe("formatEvent", () => {
@@ -145,3 +183,16 @@ describe("isDeleteTaskEvent", () => {
 		expect(isDeleteTaskEvent(formattedEvent)).toBe(true);
 	});
 });
+
+describe("isTaskEvent", () => {
+	it("ignores events which do not change tasks", () => {
+		const formattedEvent = {
+			id: 2,
+			data: { StartTimer: { taskId: 1, at: "2023-01-01T09:00:00Z" } },
+		} as unknown as FormattedTaskEvent;
+
+		expect(isTaskEvent(formattedEvent)).toBe(false);
+		expect(isTaskEvent({ id: 1, data: { DeleteTask: 1 } })).toBe(true);
+		expect(isTaskEvent({ id: 1, data: { ArchiveTask: 1 } })).toBe(true);
+	});
+});
@@ -1,9 +1,10 @@
-import type { Task } from "./task";
+import { type Task, compareDeadlines } from "./task";
 
 export enum TaskEventType {
 	CreateTask,
 	UpdateTask,
 	DeleteTask,
+	ArchiveTask,
 }
 
 export type TaskEvent =
@@ -21,6 +22,11 @@ export type TaskEvent =
 			readonly type: TaskEventType.DeleteTask;
 			readonly id: number;
 			readonly taskId: number;
+	  }
+	| {
+			readonly type: TaskEventType.ArchiveTask;
+			readonly id: number;
+			readonly taskId: number;
 	  };
 
 /** Rust-friendly format */
@@ -33,6 +39,9 @@ export type FormattedTaskEventData =
 	  }
 	| {
 			readonly DeleteTask: number;
+	  }
+	| {
+			readonly ArchiveTask: number;
 	  };
 
 export type FormattedTaskEvent = {
@@ -52,13 +61,14 @@ export const applyEvent = (tasks: Task[], event: TaskEvent): Task[] => {
 			}
 			break;
 		}
-		case TaskEventType.DeleteTask: {
+		case TaskEventType.DeleteTask:
+		case TaskEventType.ArchiveTask: {
 			const indexToDelete = tasks.findIndex((t) => t.id === event.taskId);
 			if (indexToDelete !== -1) tasks.splice(indexToDelete, 1);
 			break;
 		}
 	}
-	return tasks.sort((a, b) => Date.parse(a.deadline) - Date.parse(b.deadline));
+	return tasks.sort((a, b) => compareDeadlines(a.deadline, b.deadline));
 };
 
 export const applyEvents = (
@@ -90,6 +100,13 @@ export const formatEvent = (event: TaskEvent): FormattedTaskEvent => {
 					DeleteTask: event.taskId,
 				},
 			};
+		case TaskEventType.ArchiveTask:
+			return {
+				id: event.id,
+				data: {
+					ArchiveTask: event.taskId,
+				},
+			};
 		default:
 			throw new Error(`Unrecognized task event: ${event}`);
 	}
@@ -125,6 +142,23 @@ export const isDeleteTaskEvent = (
 	return Object.keys(event.data).includes("DeleteTask");
 };
 
+export const isArchiveTaskEvent = (
+	event: FormattedTaskEvent
+): event is FormattedTaskEvent & {
+	readonly data: {
+		readonly ArchiveTask: number;
+	};
+} => {
+	return Object.keys(event.data).includes("ArchiveTask");
+};
+
+/** Other events, such as time tracking, do not change the tasks */
+export const isTaskEvent = (event: FormattedTaskEvent): boolean =>
+	isCreateTaskEvent(event) ||
+	isUpdateTaskEvent(event) ||
+	isDeleteTaskEvent(event) ||
+	isArchiveTaskEvent(event);
+
 /** Converts from Rust-friendly format */
 export const unformatEvent = (event: FormattedTaskEvent): TaskEvent => {
 	if (isCreateTaskEvent(event)) {
@@ -148,5 +182,12 @@ export const unformatEvent = (event: FormattedTaskEvent): TaskEvent => {
 			taskId: event.data.DeleteTask,
 		};
 	}
+	if (isArchiveTaskEvent(event)) {
+		return {
+			type: TaskEventType.ArchiveTask,
+			id: event.id,
+			taskId: event.data.ArchiveTask,
+		};
+	}
 	throw new Error(`Unrecognized task event: ${event}`);
 };
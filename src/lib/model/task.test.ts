@@ -0,0 +1,47 @@
+import { describe, expect, it } from "vitest";
+import {
+	type ZonedDateTime,
+	compareDeadlines,
+	getDeadlineDate,
+	setDeadlineDate,
+} from "./task";
+
+const zoned: ZonedDateTime = {
+	local: "2023-01-01T15:30:00",
+	timeZone: "Europe/London",
+};
+
+describe("getDeadlineDate", () => {
+	it("returns the day of any kind of deadline", () => {
+		expect(getDeadlineDate("2023-01-02")).toBe("2023-01-02");
+		expect(getDeadlineDate(zoned)).toBe("2023-01-01");
+		expect(getDeadlineDate(null)).toBeNull();
+	});
+});
+
+describe("setDeadlineDate", () => {
+	it("keeps the time and zone of a zoned deadline", () => {
+		expect(setDeadlineDate(zoned, "2023-01-05")).toEqual({
+			local: "2023-01-05T15:30:00",
+			timeZone: "Europe/London",
+		});
+		expect(setDeadlineDate(null, "2023-01-05")).toBe("2023-01-05");
+	});
+
+	it("clears the deadline when the day is empty", () => {
+		expect(setDeadlineDate(zoned, "")).toBeNull();
+		expect(setDeadlineDate("2023-01-02", "")).toBeNull();
+	});
+});
+
+describe("compareDeadlines", () => {
+	it("orders dates, zoned deadlines and undated tasks", () => {
+		const deadlines = [null, "2023-01-02", zoned, "2023-01-01"];
+		expect(deadlines.sort(compareDeadlines)).toEqual([
+			"2023-01-01",
+			zoned,
+			"2023-01-02",
+			null,
+		]);
+	});
+});
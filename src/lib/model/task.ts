@@ -1,7 +1,66 @@
+export enum Status {
+	Todo = "Todo",
+	InProgress = "InProgress",
+	Waiting = "Waiting",
+	Done = "Done",
+	Cancelled = "Cancelled",
+}
+
+/** A wall-clock time in a named time zone */
+export interface ZonedDateTime {
+	/** YYYY-MM-DDTHH:MM:SS */
+	local: string;
+	timeZone: string;
+}
+
+/** A YYYY-MM-DD date, a zoned date time, or null for undated tasks */
+export type Deadline = string | ZonedDateTime | null;
+
 export interface Task {
 	id: number;
 	description: string;
-	deadline: string;
+	deadline: Deadline;
 	details: string;
-	completed: boolean;
+	status: Status;
 }
+
+/** Done or cancelled, so no longer needs doing */
+export const isClosed = (task: Task): boolean =>
+	task.status === Status.Done || task.status === Status.Cancelled;
+
+export const isZonedDeadline = (
+	deadline: Deadline
+): deadline is ZonedDateTime =>
+	typeof deadline === "object" && deadline !== null;
+
+/** The YYYY-MM-DD day the deadline falls on, in its own time zone */
+export const getDeadlineDate = (deadline: Deadline): string | null =>
+	isZonedDeadline(deadline) ? deadline.local.split("T")[0] : deadline;
+
+/** Moves the deadline to another day, keeping any time and zone. An empty day clears it. */
+export const setDeadlineDate = (deadline: Deadline, date: string): Deadline => {
+	if (date === "") {
+		return null;
+	}
+	if (isZonedDeadline(deadline)) {
+		const time = deadline.local.split("T")[1];
+		return { ...deadline, local: `${date}T${time}` };
+	}
+	return date;
+};
+
+/** Orders by wall-clock time, with a date at the start of its day and undated tasks last */
+export const compareDeadlines = (a: Deadline, b: Deadline): number => {
+	const aKey = isZonedDeadline(a) ? a.local : a;
+	const bKey = isZonedDeadline(b) ? b.local : b;
+	if (aKey === bKey) {
+		return 0;
+	}
+	if (aKey === null) {
+		return 1;
+	}
+	if (bKey === null) {
+		return -1;
+	}
+	return aKey < bKey ? -1 : 1;
+};